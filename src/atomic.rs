@@ -0,0 +1,94 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+fn random_suffix() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+/// Replace the contents of a file atomically.
+/// The new contents are written to a temporary sibling file, which is
+/// then renamed over the top of the original. The permissions of the
+/// original file are preserved.
+pub fn atomic_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().ok_or(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Path has no parent directory: {path:?}"),
+    ))?;
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid file name: {path:?}"),
+        ))?;
+    let tmp_path = dir.join(format!(".{name}.tmp-{}", random_suffix()));
+    let permissions = fs::metadata(path).ok().map(|m| m.permissions());
+    let write = || -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(permissions) = permissions {
+            fs::set_permissions(&tmp_path, permissions)?;
+        }
+        fs::rename(&tmp_path, path)
+    };
+    match write() {
+        Ok(_) => {
+            debug!("atomically replaced: {path:?}");
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// A private (0700) temporary directory that is removed when dropped.
+pub struct PrivateTempDir {
+    pub path: PathBuf,
+}
+
+impl PrivateTempDir {
+    pub fn new() -> io::Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("{}-{}", env!("CARGO_PKG_NAME"), random_suffix()));
+        DirBuilder::new().mode(0o700).create(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Create a new private (0600) file in the directory with the given contents.
+    pub fn create_file(&self, name: &str, contents: &[u8]) -> io::Result<PathBuf> {
+        let path = self.path.join(name);
+        let mut file: File = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.write_all(contents)?;
+        Ok(path)
+    }
+}
+
+impl Drop for PrivateTempDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            warn!("Failed to remove temporary directory {:?}: {e}", self.path);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dry_run::is_dry_run;
+use crate::paths::{content_hash, get_backup_dir, resolve_original_path};
+use crate::rotation::now;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+/// Who did what to which entries of a backup, and when. Only names are
+/// recorded: paths, keys and tags, never the values of secrets.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: usize,
+    pub time: u64,
+    pub uid: u32,
+    pub user: String,
+    pub hostname: String,
+    pub operation: String,
+    /// The paths (or globs) given, or none for the whole backup.
+    pub entries: Vec<String>,
+    pub keys: Vec<String>,
+    pub tags: Vec<String>,
+    /// "ok" or "failed".
+    pub status: String,
+    /// The hash of the previous record, or "" for the first one.
+    pub prev: String,
+}
+
+/// One line of .audit.ron: a record, and the hash of its serialization,
+/// which chains it to the next record.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditLine {
+    record: AuditRecord,
+    hash: String,
+}
+
+fn audit_file(backup_dir: &Path) -> PathBuf {
+    backup_dir.join(".audit.ron")
+}
+
+fn record_hash(record: &AuditRecord) -> String {
+    content_hash(
+        ron::ser::to_string(record)
+            .expect("Failed to serialize audit record")
+            .as_bytes(),
+    )
+}
+
+fn read_lines(backup_dir: &Path) -> io::Result<Vec<String>> {
+    match fs::read_to_string(audit_file(backup_dir)) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+        Ok(text) => Ok(text.lines().map(String::from).collect()),
+    }
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its whole length.
+    match unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } {
+        0 => {
+            let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+            String::from_utf8_lossy(&buf[..len]).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid always succeeds.
+    unsafe { libc::getuid() }
+}
+
+/// The name of the user, from the password database rather than $USER,
+/// which anyone can set.
+fn user_name(uid: u32) -> String {
+    // SAFETY: the entry is only read before any other call to getpwuid.
+    unsafe {
+        let entry = libc::getpwuid(uid);
+        if !entry.is_null() && !(*entry).pw_name.is_null() {
+            return std::ffi::CStr::from_ptr((*entry).pw_name)
+                .to_string_lossy()
+                .to_string();
+        }
+    }
+    std::env::var("USER").unwrap_or_default()
+}
+
+/// An operation on a backup, to be recorded in its audit log once it is done.
+pub struct Audit {
+    backup_name: String,
+    backup_dir: Option<PathBuf>,
+    operation: String,
+    entries: Vec<String>,
+    keys: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl Audit {
+    /// The audit of a command, if it may change a backup. (The backup
+    /// directory is found now, while deinit has not yet forgotten it.)
+    pub fn start(command: &str, matches: &ArgMatches) -> Option<Audit> {
+        // (`history` only changes the backup when it prunes the history)
+        let pruning = command == "history"
+            && (matches.get_flag("clear") || matches.get_one::<usize>("keep").is_some());
+        if is_dry_run() || (crate::cli::READ_ONLY_COMMANDS.contains(&command) && !pruning) {
+            return None;
+        }
+        // (these may touch several backups, so they audit each one themselves)
+        if matches!(command, "sync" | "sync-copies") {
+            return None;
+        }
+        let strings = |id| -> Vec<String> {
+            matches
+                .try_get_many::<String>(id)
+                .ok()
+                .flatten()
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default()
+        };
+        let one = |id| strings(id).into_iter().next();
+        let backup_name = one("BACKUP_NAME").or(one("add"))?;
+        let mut entries = Vec::new();
+        if !matches!(command, "init" | "deinit") {
+            for id in ["PATH", "EXISTING_PATH", "NEW_PATH", "output"] {
+                for path in strings(id) {
+                    match path.contains(['*', '?', '[']) {
+                        true => entries.push(path),
+                        false => entries.push(resolve_original_path(&path).display().to_string()),
+                    }
+                }
+            }
+        }
+        Some(Audit {
+            backup_dir: get_backup_dir(&backup_name).ok(),
+            backup_name,
+            operation: command.to_string(),
+            entries,
+            keys: strings("KEY"),
+            tags: [strings("TAG"), strings("tag")].concat(),
+        })
+    }
+
+    /// The audit of an operation on one backup, with no entries yet.
+    pub fn new(backup_name: &str, operation: &str) -> Option<Audit> {
+        if is_dry_run() {
+            return None;
+        }
+        Some(Audit {
+            backup_dir: get_backup_dir(backup_name).ok(),
+            backup_name: backup_name.to_string(),
+            operation: operation.to_string(),
+            entries: Vec::new(),
+            keys: Vec::new(),
+            tags: Vec::new(),
+        })
+    }
+
+    /// Append the record of the operation to the audit log of the backup.
+    pub fn finish(self, ok: bool) -> io::Result<()> {
+        let backup_dir = match self.backup_dir {
+            Some(dir) => dir,
+            None => match get_backup_dir(&self.backup_name) {
+                Ok(dir) => dir,
+                Err(_) if !ok => return Ok(()), // (no backup to record it in)
+                Err(e) => return Err(e),
+            },
+        };
+        if !backup_dir.is_dir() {
+            return Ok(());
+        }
+        let last = read_lines(&backup_dir)?
+            .iter()
+            .rev()
+            .find_map(|line| ron::de::from_str::<AuditLine>(line).ok());
+        let record = AuditRecord {
+            seq: last.as_ref().map_or(1, |l| l.record.seq + 1),
+            time: now(),
+            uid: uid(),
+            user: user_name(uid()),
+            hostname: hostname(),
+            operation: self.operation,
+            entries: self.entries,
+            keys: self.keys,
+            tags: self.tags,
+            status: if ok { "ok" } else { "failed" }.to_string(),
+            prev: last.map(|l| l.hash).unwrap_or_default(),
+        };
+        let line = AuditLine {
+            hash: record_hash(&record),
+            record,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(audit_file(&backup_dir))?;
+        let line = ron::ser::to_string(&line).expect("Failed to serialize audit record");
+        writeln!(file, "{line}")
+    }
+}
+
+/// The audits of an operation that may touch several backups: one record
+/// for each backup it changed, or failed to change, with those entries.
+#[derive(Default)]
+pub struct BackupAudits {
+    audits: BTreeMap<String, (Audit, bool)>,
+}
+
+impl BackupAudits {
+    /// Note that the operation touched an entry of the backup.
+    pub fn touch(&mut self, backup_name: &str, operation: &str, entry: &str, ok: bool) {
+        let Some(audit) = Audit::new(backup_name, operation) else {
+            return;
+        };
+        let (audit, all_ok) = self
+            .audits
+            .entry(backup_name.to_string())
+            .or_insert((audit, true));
+        audit.entries.push(entry.to_string());
+        *all_ok &= ok;
+    }
+
+    /// Record the operation in the audit log of every backup it touched.
+    pub fn finish(self) -> io::Result<()> {
+        self.audits
+            .into_values()
+            .try_for_each(|(audit, ok)| audit.finish(ok))
+    }
+}
+
+/// The result of checking the chain of an audit log.
+pub struct Verification {
+    pub records: usize,
+    pub last_hash: Option<String>,
+    pub problems: Vec<String>,
+}
+
+/// Check that no record of the audit log was edited, removed or inserted:
+/// every record must hash to its hash, follow on from the one before it,
+/// and be numbered one after it. Records removed from the end leave no
+/// gap, so for those give `last_hash`, the hash printed by an earlier
+/// verify, which must still be in the log.
+pub fn verify_log(backup_name: &str, last_hash: Option<&str>) -> io::Result<Verification> {
+    let lines = read_lines(&get_backup_dir(backup_name)?)?;
+    let mut problems = Vec::new();
+    let mut prev: Option<AuditLine> = None;
+    let mut hashes = Vec::new();
+    for (n, line) in (1..).zip(&lines) {
+        let line: AuditLine = match ron::de::from_str(line) {
+            Ok(line) => line,
+            Err(_) => {
+                problems.push(format!("line {n}: not a valid audit record"));
+                continue;
+            }
+        };
+        let seq = line.record.seq;
+        if record_hash(&line.record) != line.hash {
+            problems.push(format!("line {n}: record {seq} was edited"));
+        }
+        let expected_seq = prev.as_ref().map_or(1, |p| p.record.seq + 1);
+        if seq == expected_seq + 1 {
+            problems.push(format!("line {n}: record {expected_seq} is missing"));
+        } else if seq > expected_seq {
+            problems.push(format!(
+                "line {n}: records {expected_seq} to {} are missing",
+                seq - 1
+            ));
+        } else if seq < expected_seq {
+            problems.push(format!("line {n}: record {seq} is out of order"));
+        }
+        let expected_prev = prev.as_ref().map_or("", |p| p.hash.as_str());
+        if line.record.prev != expected_prev {
+            problems.push(format!(
+                "line {n}: record {seq} does not follow on from the record before it"
+            ));
+        }
+        hashes.push(line.hash.clone());
+        prev = Some(line);
+    }
+    if let Some(hash) = last_hash {
+        if !hashes.iter().any(|h| h == hash) {
+            problems.push(format!(
+                "the record with hash {hash} is missing: the log was truncated or replaced"
+            ));
+        }
+    }
+    Ok(Verification {
+        records: lines.len(),
+        last_hash: prev.map(|p| p.hash),
+        problems,
+    })
+}
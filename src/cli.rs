@@ -1,5 +1,100 @@
 use crate::config;
-use clap::{Arg, Command};
+use crate::document::Format;
+use crate::paths::LinkMode;
+use crate::subcommand::env::EnvShell;
+use crate::subcommand::restore::ConflictPolicy;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Arg, ArgGroup, Command};
+use clap_complete::Shell;
+
+/// The commands that can be run with `--dry-run`: those that print their
+/// changes instead of making them, and those that change nothing. Any
+/// other command refuses to run, rather than change anything.
+pub const DRY_RUN_COMMANDS: [&str; 30] = [
+    "init",
+    "deinit",
+    "migrate",
+    "add",
+    "link",
+    "unlink",
+    "mode",
+    "edit",
+    "get",
+    "set",
+    "unset",
+    "env",
+    "render",
+    "generate",
+    "example",
+    "schema",
+    "check",
+    "policy",
+    "stale",
+    "sync",
+    "sync-copies",
+    "restore",
+    "rm",
+    "ls",
+    "tag",
+    "history",
+    "undo",
+    "audit-log",
+    "status",
+    "completions",
+];
+
+/// The commands that never change a backup, and so are not recorded in
+/// its audit log.
+pub const READ_ONLY_COMMANDS: [&str; 11] = [
+    "ls",
+    "status",
+    "history",
+    "get",
+    "check",
+    "stale",
+    "env",
+    "run",
+    "example",
+    "completions",
+    "audit-log",
+];
+
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .num_args(1)
+        .value_parser(
+            PossibleValuesParser::new(["dotenv", "json", "yaml", "toml"])
+                .map(|s| s.parse::<Format>().expect("invalid format")),
+        )
+        .help("The format of the file (detected from the file extension by default)")
+}
+
+/// The arguments that select some of the files in a backup.
+fn selector_args(help: &'static str) -> [Arg; 3] {
+    [
+        Arg::new("PATH").num_args(1..).help(help),
+        Arg::new("tag")
+            .long("tag")
+            .value_name("TAG")
+            .action(clap::ArgAction::Append)
+            .help("Only select files with this tag (may be repeated)"),
+        Arg::new("under")
+            .long("under")
+            .value_name("DIR")
+            .help("Only select files under this directory"),
+    ]
+}
+
+const RELATIVE_HELP: &str = "Create relative symlinks, regardless of the policy of the backup";
+
+/// The flag for the commands that create symlinks.
+fn relative_arg(help: &'static str) -> Arg {
+    Arg::new("relative")
+        .long("relative")
+        .help(help)
+        .action(clap::ArgAction::SetTrue)
+}
 
 pub fn app() -> Command {
     Command::new("hushcrumbs")
@@ -39,36 +134,412 @@ pub fn app() -> Command {
                 .help("Disables all interactive confirmation (careful!)")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help("Prints the changes to files and manifests that a command would make, without making them")
+                .action(clap::ArgAction::SetTrue),
+        )
         .subcommand(
             Command::new("init")
                 .about("Creates a new backup directory")
                 .arg(Arg::new("BACKUP_NAME").required(true))
-                .arg(Arg::new("PATH").required(true)),
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("content-addressed")
+                        .long("content-addressed")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Store files by the hash of their contents, sharing identical files"),
+                )
+                .arg(relative_arg("Always create relative symlinks to the backup files")),
         )
         .subcommand(
             Command::new("deinit")
                 .about("Restores all original files and unconfigures the backup directory")
                 .arg(Arg::new("BACKUP_NAME").required(true)),
         )
+        .subcommand(
+            Command::new("migrate")
+                .about("Converts a backup to content-addressed storage, sharing identical files")
+                .arg(Arg::new("BACKUP_NAME").required(true)),
+        )
         .subcommand(
             Command::new("add")
                 .about("Adds a file to the backup and creates a symlink")
                 .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("repo-relative")
+                        .long("repo-relative")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Also record the path within its git repository, for restore --here"),
+                )
+                .arg(relative_arg(RELATIVE_HELP)),
+        )
+        .subcommand(
+            Command::new("link")
+                .about("Creates another symlink to a backed up file, eg. in a second git worktree")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("EXISTING_PATH").required(true))
+                .arg(Arg::new("NEW_PATH").required(true))
+                .arg(relative_arg(RELATIVE_HELP)),
+        )
+        .subcommand(
+            Command::new("unlink")
+                .about("Removes one of the symlinks to a backed up file, keeping the backup")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true)),
+        )
+        .subcommand(
+            Command::new("mode")
+                .about("Sets whether a backed up file is placed as a symlink, hard link or copy")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("MODE").required(true).value_parser(
+                        PossibleValuesParser::new(["symlink", "hardlink", "copy"])
+                            .map(|s| LinkMode::parse(&s).expect("invalid link mode")),
+                    ),
+                )
+                .arg(relative_arg(RELATIVE_HELP)),
+        )
+        .subcommand(
+            Command::new("tag")
+                .about("Tags backed up files, to select them by tag in restore, rm and ls")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("TAG").required(true))
+                .args(selector_args("The files to tag (paths or globs)"))
+                .group(
+                    ArgGroup::new("selector")
+                        .args(["PATH", "tag", "under"])
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("remove")
+                        .long("remove")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Remove the tag instead"),
+                ),
+        )
+        .subcommand(
+            Command::new("edit")
+                .about("Edits a backed up file in place with $EDITOR")
+                .arg(Arg::new("BACKUP_NAME").required(true))
                 .arg(Arg::new("PATH").required(true)),
         )
+        .subcommand(
+            Command::new("get")
+                .about("Prints the value of a key from a backed up dotenv, JSON, YAML or TOML file")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(Arg::new("KEY").required(true))
+                .arg(format_arg()),
+        )
+        .subcommand(
+            Command::new("set")
+                .about("Sets the value of a key in a backed up dotenv, JSON, YAML or TOML file")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(Arg::new("KEY").required(true))
+                .arg(format_arg())
+                .arg(Arg::new("VALUE").required_unless_present("stdin"))
+                .arg(
+                    Arg::new("stdin")
+                        .long("stdin")
+                        .conflicts_with("VALUE")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Read the value from stdin, rather than from the command line"),
+                ),
+        )
+        .subcommand(
+            Command::new("unset")
+                .about("Removes a key from a backed up dotenv, JSON, YAML or TOML file")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(Arg::new("KEY").required(true))
+                .arg(format_arg()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Runs a command with the variables of backed up dotenv files in its environment")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .short('f')
+                        .value_name("PATH")
+                        .action(clap::ArgAction::Append)
+                        .help("A dotenv file to load, which may be repeated (default: all .env files in the backup)"),
+                )
+                .arg(
+                    Arg::new("COMMAND")
+                        .required(true)
+                        .num_args(1..)
+                        .last(true)
+                        .help("The command to run, and its arguments (after --)"),
+                ),
+        )
+        .subcommand(
+            Command::new("env")
+                .about("Prints shell statements to export the variables of a backed up dotenv file")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("shell")
+                        .long("shell")
+                        .num_args(1)
+                        .value_parser(
+                            PossibleValuesParser::new(EnvShell::possible_values())
+                                .map(|s| s.parse::<EnvShell>().expect("invalid shell")),
+                        )
+                        .help("The shell to print statements for (default: detected from $SHELL)"),
+                )
+                .arg(
+                    Arg::new("unset")
+                        .long("unset")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print statements that unset the variables instead"),
+                ),
+        )
+        .subcommand(
+            Command::new("render")
+                .about("Renders a template, replacing {{ hc:BACKUP_NAME:PATH#KEY }} with secret values")
+                .arg(Arg::new("TEMPLATE").required(true))
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .num_args(1)
+                        .value_name("OUT")
+                        .help("Write the rendered output to a file, rather than stdout"),
+                )
+                .arg(
+                    Arg::new("add")
+                        .long("add")
+                        .num_args(1)
+                        .value_name("BACKUP_NAME")
+                        .requires("output")
+                        .help("Add the rendered output file to the named backup"),
+                ),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generates a random secret and sets it as a key in a backed up file")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(Arg::new("KEY").required(true))
+                .arg(
+                    Arg::new("length")
+                        .long("length")
+                        .num_args(1)
+                        .default_value("32")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The length of the secret (the number of words for --charset words)"),
+                )
+                .arg(
+                    Arg::new("charset")
+                        .long("charset")
+                        .num_args(1)
+                        .default_value("alnum")
+                        .value_parser(["alnum", "hex", "base64", "words"])
+                        .help("The characters to generate the secret from"),
+                )
+                .arg(
+                    Arg::new("if-missing")
+                        .long("if-missing")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Only generate the secret if the key does not already exist"),
+                ),
+        )
+        .subcommand(
+            Command::new("example")
+                .about("Writes a redacted example of a backed up dotenv file, eg. .env.example")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .num_args(1)
+                        .value_name("OUT")
+                        .help("The example file (default: PATH with .example appended)"),
+                )
+                .arg(
+                    Arg::new("hints")
+                        .long("hints")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Replace values with a hint of their type, rather than blanking them"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("hints")
+                        .help("Don't write anything, but fail if the example keys are out of date"),
+                ),
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Sets the schema that a backed up dotenv file must follow")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(Arg::new("PATH").required(true))
+                .arg(
+                    Arg::new("SCHEMA_FILE")
+                        .required_unless_present("clear")
+                        .help("The RON schema file to store in the backup"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .long("clear")
+                        .conflicts_with("SCHEMA_FILE")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Remove the schema from the backup"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Validates backed up dotenv files against their schema")
+                .arg(Arg::new("BACKUP_NAME").required(false)),
+        )
+        .subcommand(
+            Command::new("policy")
+                .about("Sets the policy of a backup: the maximum age of its secrets, and the style of its symlinks")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(
+                    Arg::new("max-age")
+                        .long("max-age")
+                        .num_args(1)
+                        .value_name("DAYS")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("The maximum age of a secret, in days, before it needs rotating"),
+                )
+                .arg(
+                    Arg::new("no-max-age")
+                        .long("no-max-age")
+                        .conflicts_with("max-age")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Remove the maximum age policy"),
+                )
+                .arg(
+                    Arg::new("relative-links")
+                        .long("relative-links")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Create relative symlinks to the backup files"),
+                )
+                .arg(
+                    Arg::new("absolute-links")
+                        .long("absolute-links")
+                        .conflicts_with("relative-links")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Create absolute symlinks to the backup files (the default)"),
+                )
+                .group(
+                    ArgGroup::new("policy")
+                        .args(["max-age", "no-max-age", "relative-links", "absolute-links"])
+                        .required(true)
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            Command::new("stale")
+                .about("Lists secrets that are older than the maximum age policy of their backup")
+                .arg(Arg::new("BACKUP_NAME").required(false))
+                .arg(
+                    Arg::new("max-age")
+                        .long("max-age")
+                        .num_args(1)
+                        .value_name("DAYS")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Override the maximum age policy, in days"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Output JSON instead of pretty tables."),
+                ),
+        )
+        .subcommand(
+            Command::new("sync")
+                .about("Adds and links the files declared in the .hushcrumbs.ron file of the current project")
+                .arg(relative_arg(RELATIVE_HELP)),
+        )
+        .subcommand(
+            Command::new("sync-copies")
+                .about("Syncs changes between tracked copies and the backup, in whichever direction they were made")
+                .arg(Arg::new("BACKUP_NAME").required(false)),
+        )
         .subcommand(
             Command::new("restore")
                 .about("Restores backup files")
                 .arg(Arg::new("BACKUP_NAME").required(true))
-                .arg(Arg::new("copy").long("copy").help("Restore file by copying, rather than symlinking").action(clap::ArgAction::SetTrue))
-                .arg(Arg::new("overwrite").long("overwrite")),
+                .arg(relative_arg(RELATIVE_HELP))
+                .args(selector_args("Only restore these files (paths or globs)"))
+                .arg(Arg::new("copy").long("copy").help("Restore files as tracked copies, rather than symlinks").action(clap::ArgAction::SetTrue))
+                .arg(
+                    Arg::new("hardlink")
+                        .long("hardlink")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Restore files as hard links (on the same filesystem as the backup)"),
+                )
+                .arg(
+                    Arg::new("symlink")
+                        .long("symlink")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Restore files as symlinks, even those restored in another mode before"),
+                )
+                .group(ArgGroup::new("mode").args(["copy", "hardlink", "symlink"]))
+                .arg(
+                    Arg::new("here")
+                        .long("here")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Restore the repository relative files into the git repository of the current directory"),
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .value_name("DIR")
+                        .conflicts_with("here")
+                        .help("Restore every file under another root directory, eg. a chroot or a mounted machine image (as copies, unless the backup is under it too)"),
+                )
+                .arg(
+                    Arg::new("on-conflict")
+                        .long("on-conflict")
+                        .value_name("POLICY")
+                        .value_parser(
+                            PossibleValuesParser::new(["skip", "overwrite", "stash", "fail", "adopt"])
+                                .map(|s| ConflictPolicy::parse(&s).expect("invalid policy")),
+                        )
+                        .help("What to do with an existing file in the way: skip it, overwrite it, stash it in a timestamped sibling, fail, or adopt its contents into the backup (asks to overwrite by default)"),
+                )
+                .arg(
+                    Arg::new("overwrite")
+                        .long("overwrite")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("on-conflict")
+                        .help("Same as --on-conflict overwrite"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Output the result for each file as JSON instead of a table."),
+                ),
         )
         .subcommand(
             Command::new("rm")
                 .visible_alias("remove")
                 .about("Removes a file from the backup")
                 .arg(Arg::new("BACKUP_NAME").required(true))
-                .arg(Arg::new("PATH").required(true))
+                .args(selector_args("The files to remove (paths or globs)"))
+                .group(
+                    ArgGroup::new("selector")
+                        .args(["PATH", "tag", "under"])
+                        .multiple(true)
+                        .required(true),
+                )
                 .arg(
                     Arg::new("delete")
                         .long("delete")
@@ -83,6 +554,72 @@ pub fn app() -> Command {
                 .visible_alias("list")
                 .about("Lists backups or files in a backup")
                 .arg(Arg::new("BACKUP_NAME").required(false))
+                .args(selector_args("Only list these files (paths or globs)").map(|a| a.requires("BACKUP_NAME")))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Output JSON instead of pretty tables."),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Lists the operations on a backup that can be undone")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Output JSON instead of pretty tables."),
+                )
+                .arg(
+                    Arg::new("keep")
+                        .long("keep")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .conflicts_with("json")
+                        .help("Forget all but the last N operations, and the old file contents they hold"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .long("clear")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with_all(["json", "keep"])
+                        .help("Forget every operation, and the old file contents they hold"),
+                ),
+        )
+        .subcommand(
+            Command::new("undo")
+                .about("Undoes the last operations (add, rm, restore or edit) on a backup")
+                .arg(Arg::new("BACKUP_NAME").required(true))
+                .arg(
+                    Arg::new("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1")
+                        .help("The number of operations to undo"),
+                ),
+        )
+        .subcommand(
+            Command::new("audit-log")
+                .about("Checks the tamper-evident audit log of the changes to a backup")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("verify")
+                        .about("Verifies that no record of the audit log was edited or removed")
+                        .arg(Arg::new("BACKUP_NAME").required(true))
+                        .arg(
+                            Arg::new("last-hash")
+                                .long("last-hash")
+                                .num_args(1)
+                                .value_name("HASH")
+                                .help("The last hash from an earlier verify, to detect records removed from the end"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Shows whether each file in a backup is linked, copied, or missing")
+                .arg(Arg::new("BACKUP_NAME").required(true))
                 .arg(
                     Arg::new("json")
                         .long("json")
@@ -107,7 +644,7 @@ pub fn app() -> Command {
                     Arg::new("shell")
                         .help("The shell to generate completions for")
                         .required(false)
-                        .value_parser(["bash","zsh","fish"])
+                        .value_parser(clap::value_parser!(Shell))
                 )
         )
 }
@@ -1,6 +1,7 @@
 #[allow(unused_imports)]
 use crate::prelude::*;
 
+use crate::dry_run::{manifest_exists, pending, pretend_write};
 use crate::get_options;
 use indexmap::IndexMap;
 
@@ -19,13 +20,16 @@ pub fn load_config() -> io::Result<Config> {
     // Config path is specified by command line argument:
     let config_path: PathBuf = get_options().config_file.clone();
     // If no config exists, return the default config:
-    if !config_path.exists() {
+    if !manifest_exists(&config_path) {
         debug!("No config file found. Loading the default/blank config.");
         return Ok(Config::default());
     }
-    // Load the config file:
-    let file = File::open(&config_path)?;
-    let config: Config = match ron::de::from_reader(file) {
+    // Load the config file (or the one saved earlier in a dry run):
+    let parsed = match pending(&config_path) {
+        Some(pending) => ron::de::from_str(&pending),
+        None => ron::de::from_reader(File::open(&config_path)?),
+    };
+    let config: Config = match parsed {
         Ok(config) => config,
         Err(_) => {
             return Err(io::Error::new(
@@ -41,6 +45,9 @@ pub fn load_config() -> io::Result<Config> {
 pub fn save_config(config: &Config) -> io::Result<()> {
     // Config path is specified by command line argument:
     let config_path: PathBuf = get_options().config_file.clone();
+    if pretend_write(&config_path, config)? {
+        return Ok(());
+    }
     let serialized = ron::ser::to_string(config).map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidData,
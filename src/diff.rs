@@ -0,0 +1,30 @@
+/// A line diff of two named texts, from the old (`-`) to the new (`+`).
+pub fn diff_lines((old_name, old): (&str, &str), (new_name, new): (&str, &str)) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    // The length of the longest common subsequence of each pair of suffixes:
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = match a[i] == b[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let mut diff = format!("--- {old_name}\n+++ {new_name}\n");
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push_str(&format!(" {}\n", a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push_str(&format!("-{}\n", a[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{}\n", b[j]));
+            j += 1;
+        }
+    }
+    diff
+}
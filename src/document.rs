@@ -0,0 +1,557 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dotenv::DotEnv;
+use serde::Serialize;
+use serde_json::Value as Json;
+use serde_yaml_ng::Value as Yaml;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+use toml_edit::{DocumentMut, Item};
+
+/// The file formats that support getting and setting individual keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Dotenv,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// Detect the format from the file extension, defaulting to dotenv.
+    pub fn detect(path: &str) -> Format {
+        match Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .as_deref()
+        {
+            Some("json") => Format::Json,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Dotenv,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = io::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dotenv" => Ok(Format::Dotenv),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unsupported format: {s}"),
+            )),
+        }
+    }
+}
+
+fn invalid_data<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e.to_string())
+}
+
+fn key_not_found(key: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("Key {key:?} not found"))
+}
+
+/// Split a key into its path segments. Keys starting with `/` are JSON
+/// pointers (RFC 6901), otherwise the segments are separated by dots.
+fn key_path(key: &str) -> io::Result<Vec<String>> {
+    let segments: Vec<String> = if let Some(pointer) = key.strip_prefix('/') {
+        pointer
+            .split('/')
+            .map(|s| s.replace("~1", "/").replace("~0", "~"))
+            .collect()
+    } else {
+        key.split('.').map(|s| s.to_string()).collect()
+    };
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid key path: {key:?}"),
+        ));
+    }
+    Ok(segments)
+}
+
+fn parse_index(segment: &str, len: usize, key: &str) -> io::Result<usize> {
+    match segment.parse::<usize>() {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(key_not_found(key)),
+    }
+}
+
+/// A parsed JSON or YAML document, edited through the segments of a key
+/// path. Both keep the order of their keys (JSON with serde_json's
+/// `preserve_order`).
+trait Tree: Sized + Serialize {
+    fn child(&self, segment: &str, key: &str) -> io::Result<&Self>;
+    fn child_mut(&mut self, segment: &str, key: &str) -> io::Result<&mut Self>;
+    /// The child, adding an empty mapping if it is missing.
+    fn child_or_insert(&mut self, segment: &str, key: &str) -> io::Result<&mut Self>;
+    /// Set the child, to a value of the same type as the one it replaces
+    /// when that is possible, otherwise to a string.
+    fn set_child(&mut self, segment: &str, key: &str, value: &str) -> io::Result<()>;
+    fn remove_child(&mut self, segment: &str, key: &str) -> io::Result<()>;
+    fn as_string(&self) -> Option<&str>;
+}
+
+impl Tree for Json {
+    fn child(&self, segment: &str, key: &str) -> io::Result<&Self> {
+        match self {
+            Json::Object(map) => map.get(segment),
+            Json::Array(array) => array.get(parse_index(segment, array.len(), key)?),
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn child_mut(&mut self, segment: &str, key: &str) -> io::Result<&mut Self> {
+        match self {
+            Json::Object(map) => map.get_mut(segment),
+            Json::Array(array) => {
+                let i = parse_index(segment, array.len(), key)?;
+                array.get_mut(i)
+            }
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn child_or_insert(&mut self, segment: &str, key: &str) -> io::Result<&mut Self> {
+        match self {
+            Json::Object(map) => Ok(map
+                .entry(segment)
+                .or_insert(Json::Object(Default::default()))),
+            _ => self.child_mut(segment, key),
+        }
+    }
+
+    fn set_child(&mut self, segment: &str, key: &str, value: &str) -> io::Result<()> {
+        let like = |existing: Option<&Json>| match existing {
+            Some(Json::Number(_)) => match serde_json::from_str(value) {
+                Ok(n @ Json::Number(_)) => n,
+                _ => Json::String(value.to_string()),
+            },
+            Some(Json::Bool(_)) => match value {
+                "true" => Json::Bool(true),
+                "false" => Json::Bool(false),
+                _ => Json::String(value.to_string()),
+            },
+            _ => Json::String(value.to_string()),
+        };
+        match self {
+            Json::Object(map) => {
+                let new = like(map.get(segment));
+                map.insert(segment.to_string(), new);
+            }
+            Json::Array(array) => {
+                let i = parse_index(segment, array.len(), key)?;
+                array[i] = like(Some(&array[i]));
+            }
+            _ => return Err(key_not_found(key)),
+        }
+        Ok(())
+    }
+
+    fn remove_child(&mut self, segment: &str, key: &str) -> io::Result<()> {
+        match self {
+            Json::Object(map) => map.shift_remove(segment).map(|_| ()),
+            Json::Array(array) => {
+                array.remove(parse_index(segment, array.len(), key)?);
+                Some(())
+            }
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn as_string(&self) -> Option<&str> {
+        self.as_str()
+    }
+}
+
+impl Tree for Yaml {
+    fn child(&self, segment: &str, key: &str) -> io::Result<&Self> {
+        match self {
+            Yaml::Mapping(map) => map.get(segment),
+            Yaml::Sequence(array) => array.get(parse_index(segment, array.len(), key)?),
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn child_mut(&mut self, segment: &str, key: &str) -> io::Result<&mut Self> {
+        match self {
+            Yaml::Mapping(map) => map.get_mut(segment),
+            Yaml::Sequence(array) => {
+                let i = parse_index(segment, array.len(), key)?;
+                array.get_mut(i)
+            }
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn child_or_insert(&mut self, segment: &str, key: &str) -> io::Result<&mut Self> {
+        match self {
+            Yaml::Mapping(map) => Ok(map
+                .entry(Yaml::String(segment.to_string()))
+                .or_insert(Yaml::Mapping(Default::default()))),
+            _ => self.child_mut(segment, key),
+        }
+    }
+
+    fn set_child(&mut self, segment: &str, key: &str, value: &str) -> io::Result<()> {
+        let like = |existing: Option<&Yaml>| match existing {
+            Some(Yaml::Number(_)) => match serde_json::from_str(value) {
+                Ok(n @ Yaml::Number(_)) => n,
+                _ => Yaml::String(value.to_string()),
+            },
+            Some(Yaml::Bool(_)) => match value {
+                "true" => Yaml::Bool(true),
+                "false" => Yaml::Bool(false),
+                _ => Yaml::String(value.to_string()),
+            },
+            _ => Yaml::String(value.to_string()),
+        };
+        match self {
+            Yaml::Mapping(map) => {
+                let new = like(map.get(segment));
+                map.insert(Yaml::String(segment.to_string()), new);
+            }
+            Yaml::Sequence(array) => {
+                let i = parse_index(segment, array.len(), key)?;
+                array[i] = like(Some(&array[i]));
+            }
+            _ => return Err(key_not_found(key)),
+        }
+        Ok(())
+    }
+
+    fn remove_child(&mut self, segment: &str, key: &str) -> io::Result<()> {
+        match self {
+            Yaml::Mapping(map) => map.shift_remove(segment).map(|_| ()),
+            Yaml::Sequence(array) => {
+                array.remove(parse_index(segment, array.len(), key)?);
+                Some(())
+            }
+            _ => None,
+        }
+        .ok_or(key_not_found(key))
+    }
+
+    fn as_string(&self) -> Option<&str> {
+        self.as_str()
+    }
+}
+
+fn tree_get<'a, T: Tree>(doc: &'a T, path: &[String], key: &str) -> io::Result<&'a T> {
+    path.iter()
+        .try_fold(doc, |current, segment| current.child(segment, key))
+}
+
+fn tree_set<T: Tree>(doc: &mut T, path: &[String], key: &str, value: &str) -> io::Result<()> {
+    let (last, parents) = path.split_last().ok_or(key_not_found(key))?;
+    let mut current = doc;
+    for segment in parents {
+        current = current.child_or_insert(segment, key)?;
+    }
+    current.set_child(last, key, value)
+}
+
+fn tree_unset<T: Tree>(doc: &mut T, path: &[String], key: &str) -> io::Result<()> {
+    let (last, parents) = path.split_last().ok_or(key_not_found(key))?;
+    let mut current = doc;
+    for segment in parents {
+        current = current.child_mut(segment, key)?;
+    }
+    current.remove_child(last, key)
+}
+
+fn tree_to_string<T: Tree>(value: &T) -> io::Result<String> {
+    match value.as_string() {
+        Some(s) => Ok(s.to_string()),
+        None => serde_json::to_string(value).map_err(invalid_data),
+    }
+}
+
+fn write_json(doc: &Json, original: &str) -> io::Result<String> {
+    // Keep compact documents compact, otherwise pretty print them, with
+    // the indentation of the first indented line (other formatting, such
+    // as spacing within lines, is not kept):
+    let mut text = match original.trim().contains('\n') {
+        true => {
+            let indent = original
+                .lines()
+                .map(|line| &line[..line.len() - line.trim_start().len()])
+                .find(|indent| !indent.is_empty())
+                .unwrap_or("  ");
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut buf = Vec::new();
+            doc.serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf, formatter,
+            ))
+            .map_err(invalid_data)?;
+            String::from_utf8(buf).map_err(invalid_data)?
+        }
+        false => serde_json::to_string(doc).map_err(invalid_data)?,
+    };
+    if original.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+fn parse_json(text: &str) -> io::Result<Json> {
+    // An empty file is treated as an empty document:
+    if text.trim().is_empty() {
+        return Ok(Json::Object(Default::default()));
+    }
+    serde_json::from_str(text).map_err(invalid_data)
+}
+
+fn parse_yaml(text: &str) -> io::Result<Yaml> {
+    match serde_yaml_ng::from_str(text).map_err(invalid_data)? {
+        Yaml::Null => Ok(Yaml::Mapping(Default::default())),
+        doc => Ok(doc),
+    }
+}
+
+/// Find what rewriting the YAML would lose: comments, or anchors and
+/// aliases (which are expanded). This errs on the side of finding them,
+/// eg. a `#` line in a block scalar counts as a comment.
+fn yaml_lossy_parts(text: &str) -> Option<&'static str> {
+    for line in text.lines() {
+        let mut quote = None;
+        let mut prev = ' ';
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            let starts_token = prev.is_whitespace() || "[{,".contains(prev);
+            match (quote, c) {
+                (Some('"'), '\\') => {
+                    chars.next();
+                }
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => (),
+                (None, '\'' | '"') if starts_token || prev == ':' => quote = Some(c),
+                (None, '#') if prev.is_whitespace() => return Some("comments"),
+                (None, '&' | '*')
+                    if starts_token
+                        && chars
+                            .peek()
+                            .is_some_and(|n| n.is_alphanumeric() || *n == '_') =>
+                {
+                    return Some("anchors or aliases")
+                }
+                _ => (),
+            }
+            prev = c;
+        }
+    }
+    None
+}
+
+/// YAML is rewritten from its parsed form, so refuse to write a file
+/// that would lose anything but its layout.
+fn check_yaml_writable(text: &str) -> io::Result<()> {
+    match yaml_lossy_parts(text) {
+        Some(parts) => Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("The YAML file has {parts}, which would be lost by rewriting it. Use `edit` to change it instead."),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn write_yaml(doc: &Yaml) -> io::Result<String> {
+    serde_yaml_ng::to_string(doc).map_err(invalid_data)
+}
+
+fn toml_get<'a>(doc: &'a DocumentMut, path: &[String], key: &str) -> io::Result<&'a Item> {
+    let mut current = doc.as_item();
+    for segment in path {
+        current = match segment.parse::<usize>() {
+            Ok(i) if current.is_array() || current.is_array_of_tables() => current.get(i),
+            _ => current.get(segment),
+        }
+        .ok_or(key_not_found(key))?;
+    }
+    Ok(current)
+}
+
+fn toml_to_string(item: &Item) -> String {
+    match item.as_str() {
+        Some(s) => s.to_string(),
+        None => item.to_string().trim().to_string(),
+    }
+}
+
+fn toml_value_like(existing: Option<&Item>, value: &str) -> toml_edit::Value {
+    let mut new = match existing.and_then(|e| e.as_value()) {
+        Some(toml_edit::Value::Integer(_)) => value
+            .parse::<i64>()
+            .map(toml_edit::Value::from)
+            .unwrap_or(value.into()),
+        Some(toml_edit::Value::Float(_)) => value
+            .parse::<f64>()
+            .map(toml_edit::Value::from)
+            .unwrap_or(value.into()),
+        Some(toml_edit::Value::Boolean(_)) => value
+            .parse::<bool>()
+            .map(toml_edit::Value::from)
+            .unwrap_or(value.into()),
+        _ => value.into(),
+    };
+    // Keep the whitespace and comments surrounding the old value:
+    if let Some(old) = existing.and_then(|e| e.as_value()) {
+        *new.decor_mut() = old.decor().clone();
+    }
+    new
+}
+
+fn toml_set(doc: &mut DocumentMut, path: &[String], key: &str, value: &str) -> io::Result<()> {
+    let (last, parents) = path.split_last().ok_or(key_not_found(key))?;
+    let mut current = doc.as_item_mut();
+    for segment in parents {
+        if let (Ok(i), true) = (
+            segment.parse::<usize>(),
+            current.is_array() || current.is_array_of_tables(),
+        ) {
+            current = current.get_mut(i).ok_or(key_not_found(key))?;
+            continue;
+        }
+        // Create any missing tables along the way:
+        let new_table = if current.is_table() {
+            toml_edit::table()
+        } else {
+            Item::Value(toml_edit::InlineTable::new().into())
+        };
+        let table = current.as_table_like_mut().ok_or(key_not_found(key))?;
+        if !table.contains_key(segment) {
+            table.insert(segment, new_table);
+        }
+        current = table.get_mut(segment).ok_or(key_not_found(key))?;
+    }
+    if let Some(array) = current.as_array_mut() {
+        let i = parse_index(last, array.len(), key)?;
+        let new = toml_value_like(array.get(i).cloned().map(Item::Value).as_ref(), value);
+        array.replace(i, new);
+    } else if let Some(table) = current.as_table_like_mut() {
+        let new = toml_value_like(table.get(last), value);
+        table.insert(last, Item::Value(new));
+    } else {
+        return Err(key_not_found(key));
+    }
+    Ok(())
+}
+
+fn toml_unset(doc: &mut DocumentMut, path: &[String], key: &str) -> io::Result<()> {
+    let (last, parents) = path.split_last().ok_or(key_not_found(key))?;
+    // Check the whole path exists first, because get_mut creates missing tables:
+    toml_get(doc, path, key)?;
+    let mut current = doc.as_item_mut();
+    for segment in parents {
+        current = match segment.parse::<usize>() {
+            Ok(i) if current.is_array() || current.is_array_of_tables() => current.get_mut(i),
+            _ => current.get_mut(segment),
+        }
+        .ok_or(key_not_found(key))?;
+    }
+    if let Some(array) = current.as_array_mut() {
+        array.remove(parse_index(last, array.len(), key)?);
+    } else if let Some(tables) = current.as_array_of_tables_mut() {
+        tables.remove(parse_index(last, tables.len(), key)?);
+    } else if let Some(table) = current.as_table_like_mut() {
+        table.remove(last).ok_or(key_not_found(key))?;
+    } else {
+        return Err(key_not_found(key));
+    }
+    Ok(())
+}
+
+/// Get the value of a key from the text of a document.
+/// Strings are returned as is, other values are serialized in the
+/// document's own format.
+pub fn get(format: Format, text: &str, key: &str) -> io::Result<String> {
+    match format {
+        Format::Dotenv => DotEnv::parse(text)?
+            .get(key)
+            .map(|v| v.to_string())
+            .ok_or(key_not_found(key)),
+        Format::Json => {
+            let doc = parse_json(text)?;
+            tree_get(&doc, &key_path(key)?, key).and_then(tree_to_string)
+        }
+        Format::Yaml => {
+            let doc = parse_yaml(text)?;
+            tree_get(&doc, &key_path(key)?, key).and_then(tree_to_string)
+        }
+        Format::Toml => {
+            let doc: DocumentMut = text.parse().map_err(invalid_data)?;
+            toml_get(&doc, &key_path(key)?, key).map(toml_to_string)
+        }
+    }
+}
+
+/// Set the value of a key, returning the new text of the document.
+pub fn set(format: Format, text: &str, key: &str, value: &str) -> io::Result<String> {
+    match format {
+        Format::Dotenv => {
+            let mut dotenv = DotEnv::parse(text)?;
+            dotenv.set(key, value)?;
+            Ok(dotenv.to_string())
+        }
+        Format::Json => {
+            let mut doc = parse_json(text)?;
+            tree_set(&mut doc, &key_path(key)?, key, value)?;
+            write_json(&doc, text)
+        }
+        Format::Yaml => {
+            check_yaml_writable(text)?;
+            let mut doc = parse_yaml(text)?;
+            tree_set(&mut doc, &key_path(key)?, key, value)?;
+            write_yaml(&doc)
+        }
+        Format::Toml => {
+            let mut doc: DocumentMut = text.parse().map_err(invalid_data)?;
+            toml_set(&mut doc, &key_path(key)?, key, value)?;
+            Ok(doc.to_string())
+        }
+    }
+}
+
+/// Remove a key, returning the new text of the document.
+pub fn unset(format: Format, text: &str, key: &str) -> io::Result<String> {
+    match format {
+        Format::Dotenv => {
+            let mut dotenv = DotEnv::parse(text)?;
+            if !dotenv.unset(key) {
+                return Err(key_not_found(key));
+            }
+            Ok(dotenv.to_string())
+        }
+        Format::Json => {
+            let mut doc = parse_json(text)?;
+            tree_unset(&mut doc, &key_path(key)?, key)?;
+            write_json(&doc, text)
+        }
+        Format::Yaml => {
+            check_yaml_writable(text)?;
+            let mut doc = parse_yaml(text)?;
+            tree_unset(&mut doc, &key_path(key)?, key)?;
+            write_yaml(&doc)
+        }
+        Format::Toml => {
+            let mut doc: DocumentMut = text.parse().map_err(invalid_data)?;
+            toml_unset(&mut doc, &key_path(key)?, key)?;
+            Ok(doc.to_string())
+        }
+    }
+}
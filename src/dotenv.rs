@@ -0,0 +1,341 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use std::io;
+
+/// The quoting style used for a value in a dotenv file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    quote: Quote,
+    // Everything before the value (indentation, `export`, key, `=`):
+    head: String,
+    // Everything after the value (whitespace, inline comment):
+    tail: String,
+    // The original text, until the value is changed:
+    original: Option<String>,
+}
+
+#[derive(Debug)]
+enum Line {
+    Entry(Entry),
+    Other(String),
+}
+
+/// A parsed dotenv file. Comments, blank lines, ordering and quoting
+/// style are all preserved when it is written back out again.
+#[derive(Debug, Default)]
+pub struct DotEnv {
+    lines: Vec<Line>,
+    trailing_newline: bool,
+}
+
+/// Is this the name of a dotenv file? (`.env`, `.env.local`, `prod.env`,
+/// but not the `.env.schema` or `.env.example` that go with them)
+pub fn is_dotenv_file_name(path: &str) -> bool {
+    match std::path::Path::new(path)
+        .file_name()
+        .and_then(|f| f.to_str())
+    {
+        Some(name) => match name.strip_prefix(".env.") {
+            Some(variant) => !variant.is_empty() && !matches!(variant, "schema" | "example"),
+            None => name.ends_with(".env"),
+        },
+        None => false,
+    }
+}
+
+pub fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+fn is_safe_unquoted(value: &str) -> bool {
+    !value
+        .chars()
+        .any(|c| c.is_whitespace() || "#\"'\\$`=<>|&;()".contains(c))
+}
+
+fn escape_double(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '$' => escaped.push_str("\\$"),
+            '`' => escaped.push_str("\\`"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Format a value for writing, keeping the preferred quoting style if
+/// it can represent the value.
+fn format_value(value: &str, preferred: Quote) -> String {
+    match preferred {
+        Quote::None if is_safe_unquoted(value) => value.to_string(),
+        Quote::Single if !value.contains('\'') && !value.contains('\n') => {
+            format!("'{value}'")
+        }
+        _ => format!("\"{}\"", escape_double(value)),
+    }
+}
+
+/// Is the value written as a finite number? (Rust also parses eg. `inf`
+/// and `NaN` as floats, which would be misleading hints for secrets.)
+fn is_number(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'))
+        && value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+fn type_hint(value: &str) -> &'static str {
+    if value.is_empty() {
+        ""
+    } else if is_number(value) {
+        "<number>"
+    } else if matches!(
+        value.to_lowercase().as_str(),
+        "true" | "false" | "yes" | "no" | "on" | "off"
+    ) {
+        "<boolean>"
+    } else if value.contains("://") {
+        "<url>"
+    } else {
+        "<string>"
+    }
+}
+
+impl Entry {
+    fn raw(&self) -> String {
+        match &self.original {
+            Some(original) => original.clone(),
+            None => format!(
+                "{}{}{}",
+                self.head,
+                format_value(&self.value, self.quote),
+                self.tail
+            ),
+        }
+    }
+}
+
+/// Parse the value part of an entry, which starts at `rest`. Quoted values
+/// may continue onto the following lines, which are consumed from `lines`.
+/// Returns the decoded value, the quote style, the tail, and the raw text
+/// of the value.
+fn parse_value<'a>(
+    rest: &str,
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Option<(String, Quote, String, String)> {
+    let mut raw = rest.to_string();
+    match rest.chars().next() {
+        Some('\'') => loop {
+            if let Some(end) = raw[1..].find('\'') {
+                let value = raw[1..end + 1].to_string();
+                let tail = raw[end + 2..].to_string();
+                return Some((value, Quote::Single, tail, raw));
+            }
+            raw.push('\n');
+            raw.push_str(lines.next()?);
+        },
+        Some('"') => loop {
+            let mut value = String::new();
+            let mut chars = raw.char_indices().skip(1);
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        let tail = raw[i + 1..].to_string();
+                        return Some((value, Quote::Double, tail, raw));
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 'r')) => value.push('\r'),
+                        Some((_, 't')) => value.push('\t'),
+                        Some((_, c @ ('\\' | '"' | '$' | '`'))) => value.push(c),
+                        Some((_, c)) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => value.push('\\'),
+                    },
+                    c => value.push(c),
+                }
+            }
+            raw.push('\n');
+            raw.push_str(lines.next()?);
+        },
+        _ => {
+            // Unquoted values end at an inline comment (whitespace followed by #):
+            let end = rest
+                .char_indices()
+                .find(|(i, c)| *c == '#' && rest[..*i].ends_with(|p: char| p.is_whitespace()))
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            let value = rest[..end].trim_end();
+            let tail = rest[value.len()..].to_string();
+            Some((value.to_string(), Quote::None, tail, raw))
+        }
+    }
+}
+
+impl DotEnv {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut dotenv = DotEnv {
+            lines: Vec::new(),
+            trailing_newline: text.ends_with('\n') || text.is_empty(),
+        };
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                dotenv.lines.push(Line::Other(line.to_string()));
+                continue;
+            }
+            let without_export = trimmed
+                .strip_prefix("export")
+                .filter(|s| s.starts_with(|c: char| c.is_whitespace()))
+                .map(|s| s.trim_start())
+                .unwrap_or(trimmed);
+            let (key, rest) = match without_export.split_once('=') {
+                Some((key, rest)) if is_valid_key(key.trim_end()) => (key.trim_end(), rest),
+                _ => {
+                    // Not something we understand, so leave it alone:
+                    dotenv.lines.push(Line::Other(line.to_string()));
+                    continue;
+                }
+            };
+            let value_start = rest.len() - rest.trim_start().len();
+            let head = format!(
+                "{}{}",
+                &line[..line.len() - rest.len()],
+                &rest[..value_start]
+            );
+            let (value, quote, tail, raw) =
+                parse_value(&rest[value_start..], &mut lines).ok_or(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unterminated quoted value for key: {key}"),
+                ))?;
+            dotenv.lines.push(Line::Entry(Entry {
+                key: key.to_string(),
+                value,
+                quote,
+                original: Some(format!("{head}{raw}")),
+                head,
+                tail,
+            }));
+        }
+        Ok(dotenv)
+    }
+
+    /// All of the entries, in file order. If a key is repeated, the
+    /// last one wins, just as it would when sourced by a shell.
+    pub fn entries(&self) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = Vec::new();
+        for line in &self.lines {
+            if let Line::Entry(entry) = line {
+                entries.retain(|e| e.key != entry.key);
+                entries.push(entry);
+            }
+        }
+        entries
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().rev().find_map(|line| match line {
+            Line::Entry(entry) if entry.key == key => Some(entry.value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set the value of a key. An existing key is updated in place,
+    /// keeping its quoting style when possible; a new key is appended.
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        if !is_valid_key(key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid key name: {key:?}"),
+            ));
+        }
+        let existing = self.lines.iter_mut().rev().find_map(|line| match line {
+            Line::Entry(entry) if entry.key == key => Some(entry),
+            _ => None,
+        });
+        match existing {
+            Some(entry) => {
+                entry.value = value.to_string();
+                entry.original = None;
+            }
+            None => {
+                self.lines.push(Line::Entry(Entry {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    quote: Quote::None,
+                    head: format!("{key}="),
+                    tail: String::new(),
+                    original: None,
+                }));
+                self.trailing_newline = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace every value with a blank, or with a hint of its type,
+    /// keeping the keys and comments.
+    pub fn redact(&mut self, hints: bool) {
+        for line in self.lines.iter_mut() {
+            if let Line::Entry(entry) = line {
+                entry.value = if hints {
+                    type_hint(&entry.value).to_string()
+                } else {
+                    String::new()
+                };
+                entry.original = None;
+            }
+        }
+    }
+
+    /// Remove every occurrence of a key. Returns false if it was not found.
+    pub fn unset(&mut self, key: &str) -> bool {
+        let before = self.lines.len();
+        self.lines
+            .retain(|line| !matches!(line, Line::Entry(entry) if entry.key == key));
+        self.lines.len() != before
+    }
+}
+
+impl std::fmt::Display for DotEnv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| match line {
+                Line::Entry(entry) => entry.raw(),
+                Line::Other(raw) => raw.clone(),
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))?;
+        if self.trailing_newline && !lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::diff::diff_lines;
+use crate::get_options;
+use crate::history;
+use crate::paths::Paths;
+use crate::transaction::Undo;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The manifests (paths.ron and the config) written in a dry run, which
+/// are kept in memory instead, so that the rest of the command sees them.
+static PENDING: Lazy<Mutex<HashMap<PathBuf, String>>> = Lazy::new(Default::default);
+
+/// Was the global `--dry-run` given? Then changes are printed, not made.
+pub fn is_dry_run() -> bool {
+    get_options().dry_run
+}
+
+/// In a dry run, print the change, and return true (to skip making it).
+pub fn pretend(change: impl FnOnce() -> String) -> bool {
+    let dry_run = is_dry_run();
+    if dry_run {
+        println!("Would {}", change());
+    }
+    dry_run
+}
+
+/// The contents of a manifest written earlier in a dry run, if any.
+pub fn pending(path: &Path) -> Option<String> {
+    PENDING.lock().expect("poisoned lock").get(path).cloned()
+}
+
+/// Does the manifest exist (or would it, in a dry run)?
+pub fn manifest_exists(path: &Path) -> bool {
+    path.exists() || pending(path).is_some()
+}
+
+/// In a dry run, print the changes that writing the manifest would make,
+/// keep it in memory, and return true (to skip writing it).
+pub fn pretend_write<T: Serialize + DeserializeOwned>(path: &Path, value: &T) -> io::Result<bool> {
+    if !is_dry_run() {
+        return Ok(false);
+    }
+    let pretty = |value: &T| {
+        ron::ser::to_string_pretty(value, Default::default()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to serialize {}: {e}", path.display()),
+            )
+        })
+    };
+    let old = match pending(path) {
+        Some(old) => Some(old),
+        None => fs::read_to_string(path).ok(),
+    };
+    let old = old
+        .and_then(|old| ron::de::from_str::<T>(&old).ok())
+        .map(|old| pretty(&old))
+        .transpose()?
+        .unwrap_or_default();
+    let new = pretty(value)?;
+    let name = path.display().to_string();
+    // (only the changed lines, without the header)
+    let diff = diff_lines((&name, &old), (&name, &new));
+    let changes: Vec<&str> = diff
+        .lines()
+        .skip(2)
+        .filter(|line| !line.starts_with(' '))
+        .collect();
+    if !changes.is_empty() {
+        println!("Would write {name}:\n  {}", changes.join("\n  "));
+    }
+    let compact = ron::ser::to_string(value).expect("Failed to serialize");
+    PENDING
+        .lock()
+        .expect("poisoned lock")
+        .insert(path.to_path_buf(), compact);
+    Ok(true)
+}
+
+// Outside of a dry run, each change is recorded in the history of the
+// current operation, so that it can be undone.
+
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    if pretend(|| format!("create the directory {}", path.display())) {
+        return Ok(());
+    }
+    fs::create_dir(path)?;
+    history::record(Undo::RemoveDir(path.to_path_buf()));
+    Ok(())
+}
+
+pub fn create_dir_all(path: &Path) -> io::Result<()> {
+    if pretend(|| format!("create the directory {}", path.display())) {
+        return Ok(());
+    }
+    let mut missing: Vec<&Path> = path.ancestors().take_while(|d| !d.exists()).collect();
+    fs::create_dir_all(path)?;
+    missing.reverse();
+    for dir in missing {
+        history::record(Undo::RemoveDir(dir.to_path_buf()));
+    }
+    Ok(())
+}
+
+pub fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    if pretend(|| format!("copy {} to {}", from.display(), to.display())) {
+        return Ok(());
+    }
+    let undo = Undo::write(to)?;
+    fs::copy(from, to)?;
+    history::record(undo);
+    Ok(())
+}
+
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    if pretend(|| format!("remove {}", path.display())) {
+        return Ok(());
+    }
+    let undo = Undo::removal(path)?;
+    fs::remove_file(path)?;
+    history::record(undo);
+    Ok(())
+}
+
+/// Create a symlink at `link` to a file in the backup, in the style of
+/// symlink that the backup wants.
+pub fn symlink(paths: &Paths, backup_file: &Path, link: &Path) -> io::Result<()> {
+    match pretend(|| {
+        format!(
+            "create a symlink at {} to {}",
+            link.display(),
+            backup_file.display()
+        )
+    }) {
+        true => Ok(()),
+        false => {
+            paths.symlink(backup_file, link)?;
+            history::record(Undo::Remove(link.to_path_buf()));
+            Ok(())
+        }
+    }
+}
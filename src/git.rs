@@ -0,0 +1,85 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::paths::RepoPath;
+use std::fs::canonicalize;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run git in the given directory, returning its trimmed output.
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "git {} failed in {}: {}",
+                args.join(" "),
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The top level directory of the git repository containing `dir`.
+pub fn repo_root(dir: &Path) -> io::Result<PathBuf> {
+    git(dir, &["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// The URL of the `origin` remote (or else the first remote) of the
+/// repository containing `dir`.
+pub fn remote_url(dir: &Path) -> io::Result<String> {
+    let remotes = git(dir, &["remote"])?;
+    let remote = match remotes.lines().find(|r| *r == "origin") {
+        Some(origin) => origin,
+        None => remotes.lines().next().ok_or(io::Error::new(
+            ErrorKind::NotFound,
+            format!("The git repository has no remotes: {}", dir.display()),
+        ))?,
+    };
+    git(dir, &["remote", "get-url", remote])
+}
+
+/// Normalize a remote URL, so that the SSH and HTTPS URLs of the same
+/// repository are equal: `git@github.com:a/b.git` -> `github.com/a/b`
+pub fn normalize_remote(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (url, scp_like) = match url.split_once("://") {
+        Some((_, rest)) => (rest, false),
+        None => (url, true),
+    };
+    let url = match url.split_once('@') {
+        Some((_, rest)) => rest,
+        None => url,
+    };
+    // An scp-like URL separates the host and path with a colon:
+    match (scp_like, url.split_once(':')) {
+        (true, Some((host, path))) => format!("{host}/{path}"),
+        _ => url.to_string(),
+    }
+}
+
+/// Find where the file is within its git repository. The file (and its
+/// parent directories) need not exist yet.
+pub fn repo_path(absolute_path: &Path) -> io::Result<RepoPath> {
+    let dir = absolute_path
+        .ancestors()
+        .skip(1)
+        .find(|d| d.is_dir())
+        .expect("could not get parent directory");
+    let root = canonicalize(repo_root(dir)?)?;
+    let path = absolute_path.strip_prefix(&root).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{absolute_path:?} is not inside the git repository {root:?}"),
+        )
+    })?;
+    Ok(RepoPath {
+        remote: normalize_remote(&remote_url(dir)?),
+        path: path.to_str().expect("failed to_str on path").to_string(),
+    })
+}
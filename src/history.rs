@@ -0,0 +1,218 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::atomic::atomic_write;
+use crate::dry_run::{is_dry_run, pretend};
+use crate::paths::get_backup_dir;
+use crate::placement::relink_hardlinks;
+use crate::rotation::now;
+use crate::transaction::{undo_all, Undo};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The changes made to the filesystem by the current operation.
+static JOURNAL: Lazy<Mutex<Vec<Undo>>> = Lazy::new(Default::default);
+
+/// Note a change made by the current operation, and how to take it back.
+pub fn record(undo: Undo) {
+    JOURNAL.lock().expect("poisoned lock").push(undo);
+}
+
+/// One operation in the history of a backup: one line of .history.ron.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Operation {
+    pub seq: usize,
+    pub time: u64,
+    pub command: String,
+    /// The operation this one took back, if it is an undo.
+    #[serde(default)]
+    pub undoes: Option<usize>,
+    /// paths.ron before and after the operation.
+    #[serde(default)]
+    paths_before: Option<String>,
+    #[serde(default)]
+    paths_after: Option<String>,
+    #[serde(default)]
+    undo: Vec<Undo>,
+}
+
+fn history_file(backup_dir: &Path) -> std::path::PathBuf {
+    backup_dir.join(".history.ron")
+}
+
+fn read_paths(backup_dir: &Path) -> Option<String> {
+    fs::read_to_string(backup_dir.join("paths.ron")).ok()
+}
+
+/// Every operation in the history of the backup, oldest first.
+pub fn read_history(backup_name: &str) -> io::Result<Vec<Operation>> {
+    let file = match fs::File::open(history_file(&get_backup_dir(backup_name)?)) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        file => file?,
+    };
+    BufReader::new(file)
+        .lines()
+        .map(|line| {
+            ron::de::from_str(&line?)
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "Failed to parse .history.ron"))
+        })
+        .collect()
+}
+
+/// Append an operation to the history of the backup. (The history has the
+/// previous contents of files, so it is private, like the backup files.)
+fn append(backup_dir: &Path, operation: &Operation) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(history_file(backup_dir))?;
+    let line = ron::ser::to_string(operation).expect("Failed to serialize operation");
+    writeln!(file, "{line}")
+}
+
+/// Run an operation on the backup, and record it in the history of the
+/// backup, with what it changed, so that it can be undone. (Nothing is
+/// recorded in a dry run, or if nothing was changed.)
+pub fn record_operation<T>(
+    backup_name: &str,
+    command: &str,
+    run: impl FnOnce() -> io::Result<T>,
+) -> io::Result<T> {
+    if is_dry_run() {
+        return run();
+    }
+    let backup_dir = get_backup_dir(backup_name)?;
+    let paths_before = read_paths(&backup_dir);
+    JOURNAL.lock().expect("poisoned lock").clear();
+    let result = run();
+    let undo = std::mem::take(&mut *JOURNAL.lock().expect("poisoned lock"));
+    let paths_after = read_paths(&backup_dir);
+    if undo.is_empty() && paths_before == paths_after {
+        return result;
+    }
+    let seq = read_history(backup_name)?.last().map_or(1, |op| op.seq + 1);
+    append(
+        &backup_dir,
+        &Operation {
+            seq,
+            time: now(),
+            command: command.to_string(),
+            undoes: None,
+            paths_before,
+            paths_after,
+            undo,
+        },
+    )?;
+    result
+}
+
+/// The operations that can still be undone, newest first.
+fn undoable(history: &[Operation]) -> Vec<&Operation> {
+    history
+        .iter()
+        .rev()
+        .filter(|op| op.undoes.is_none() && !is_undone(history, op.seq))
+        .collect()
+}
+
+/// Has the operation been undone?
+pub fn is_undone(history: &[Operation], seq: usize) -> bool {
+    history.iter().any(|op| op.undoes == Some(seq))
+}
+
+/// Take back the last `count` operations on the backup, newest first.
+/// Returns the commands that were undone.
+pub fn undo_operations(backup_name: &str, count: usize) -> io::Result<Vec<String>> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    let history = read_history(backup_name)?;
+    let operations = undoable(&history);
+    if operations.len() < count {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Only {} operation(s) in the history of backup '{backup_name}' can be undone",
+                operations.len()
+            ),
+        ));
+    }
+    let next = history.last().map_or(1, |op| op.seq + 1);
+    let mut undone = Vec::new();
+    let mut paths = read_paths(&backup_dir);
+    for (seq, op) in (next..).zip(operations.into_iter().take(count)) {
+        // Anything else that changed paths.ron since would be lost:
+        if paths != op.paths_after {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "paths.ron was changed since `{}` by a command that is not in the history, so it cannot be undone",
+                    op.command
+                ),
+            ));
+        }
+        paths = op.paths_before.clone();
+        let undoing = || {
+            let mut changed: Vec<String> = op
+                .undo
+                .iter()
+                .map(|undo| undo.path().display().to_string())
+                .collect();
+            changed.dedup();
+            changed.push("paths.ron".to_string());
+            format!("undo `{}`, restoring {}", op.command, changed.join(", "))
+        };
+        if pretend(undoing) {
+            undone.push(op.command.clone());
+            continue;
+        }
+        undo_all(op.undo.iter().cloned())?;
+        let paths_file = backup_dir.join("paths.ron");
+        match &op.paths_before {
+            Some(paths) => atomic_write(&paths_file, paths.as_bytes())?,
+            None => match fs::remove_file(&paths_file) {
+                Err(e) if e.kind() == ErrorKind::NotFound => (),
+                result => result?,
+            },
+        }
+        // (a file written back is no longer the hardlink it was)
+        relink_hardlinks(backup_name)?;
+        append(
+            &backup_dir,
+            &Operation {
+                seq,
+                time: now(),
+                command: format!("undo {}", op.command),
+                undoes: Some(op.seq),
+                paths_before: None,
+                paths_after: None,
+                undo: Vec::new(),
+            },
+        )?;
+        undone.push(op.command.clone());
+    }
+    Ok(undone)
+}
+
+/// Forget all but the newest `keep` operations in the history of the
+/// backup, with the previous contents of files that they hold, so that
+/// they can no longer be undone. Returns the number of operations forgotten.
+pub fn prune_history(backup_name: &str, keep: usize) -> io::Result<usize> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    let history = read_history(backup_name)?;
+    let forget = history.len().saturating_sub(keep);
+    if forget == 0 || pretend(|| format!("forget the oldest {forget} operation(s) in the history"))
+    {
+        return Ok(forget);
+    }
+    let kept: String = history[forget..]
+        .iter()
+        .map(|op| ron::ser::to_string(op).expect("Failed to serialize operation") + "\n")
+        .collect();
+    atomic_write(&history_file(&backup_dir), kept.as_bytes())?;
+    Ok(forget)
+}
@@ -1,22 +1,59 @@
+use audit::Audit;
 use clap_complete::shells::Shell;
 use confirm::{confirm, ConfirmProps};
+use document::Format;
+use history::{prune_history, record_operation, undo_operations};
 use once_cell::sync::OnceCell;
-use std::io;
+use paths::{Layout, LinkMode};
+use selector::Selector;
+use std::io::{self, Read};
 use std::{path::PathBuf, str::FromStr};
 use subcommand::{
-    add::add_to_backup,
+    add::{add_repo_relative, add_to_backup},
+    audit::verify_audit_log,
+    check::{check_backups, set_schema},
+    copies::sync_copies,
+    edit::edit_backup_file,
+    env::{env_statements, EnvShell},
+    example::{check_example, write_example},
+    generate::generate_secret,
+    history::print_history,
     init::{deinit_backup, init_backup},
+    key::{get_key, set_key, unset_key},
+    link::{link_file, set_link_mode, unlink_file},
     list::{list_backup_files, list_backups},
-    remove::remove_from_backup,
-    restore::restore_backup,
+    migrate::migrate_backup,
+    policy::{set_policy, PolicyUpdate},
+    remove::{remove_from_backup, select_files},
+    render::render_template,
+    restore::{restore_backup, restore_here, ConflictPolicy, RestoreReport},
+    run::run_with_env,
+    stale::report_stale,
+    status::backup_status,
+    sync::sync_project,
+    tag::tag_files,
 };
 
+mod atomic;
+mod audit;
 mod cli;
 mod config;
 mod confirm;
+mod diff;
+mod document;
+mod dotenv;
+mod dry_run;
+mod git;
+mod history;
 mod paths;
+mod placement;
 mod prelude;
+mod project;
+mod rotation;
+mod schema;
+mod selector;
 mod subcommand;
+mod transaction;
 #[macro_use]
 extern crate prettytable;
 
@@ -28,6 +65,8 @@ use prelude::*;
 pub struct Options {
     config_file: PathBuf,
     no_confirm: bool,
+    relative: bool,
+    dry_run: bool,
 }
 /// Globally shared Options instance:
 static OPTIONS: OnceCell<Options> = OnceCell::new();
@@ -44,6 +83,12 @@ fn main() {
         .set(Options {
             config_file: PathBuf::from(matches.get_one::<String>("config").expect("no config arg")),
             no_confirm: matches.get_flag("no-confirm"),
+            // (only the commands that create symlinks have --relative)
+            relative: matches
+                .subcommand()
+                .and_then(|(_, m)| m.try_get_one::<bool>("relative").ok().flatten())
+                .is_some_and(|relative| *relative),
+            dry_run: matches.get_flag("dry-run"),
         })
         .expect("Options can only be set once");
 
@@ -70,13 +115,31 @@ fn main() {
         return;
     }
 
+    // A command that can't print its changes must not make them in a dry run:
+    let dry_run = get_options().dry_run;
+    let command = matches.subcommand_name().unwrap_or_default();
+    if dry_run && !cli::DRY_RUN_COMMANDS.contains(&command) {
+        eprintln!("The {command} command does not support --dry-run.");
+        std::process::exit(1);
+    }
+
+    // Commands that change a backup are recorded in its audit log:
+    let audit = matches
+        .subcommand()
+        .and_then(|(command, sub_matches)| Audit::start(command, sub_matches));
+
     // Handle the subcommands:
     eprintln!("");
     let exit_code = match matches.subcommand() {
         Some(("init", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
             let path = sub_matches.get_one::<String>("PATH");
-            match init_backup(backup_name, path.map(|s| s.as_str())) {
+            let layout = match sub_matches.get_flag("content-addressed") {
+                true => Layout::Content,
+                false => Layout::PathHash,
+            };
+            match init_backup(backup_name, path.map(|s| s.as_str()), layout) {
+                Ok(_) if dry_run => 0,
                 Ok(_) => {
                     info!("Backup '{}' initialized successfully.", backup_name);
                     0
@@ -90,6 +153,7 @@ fn main() {
         Some(("deinit", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
             match deinit_backup(backup_name) {
+                Ok(_) if dry_run => 0,
                 Ok(_) => {
                     info!("Backup '{}' removed from config.", backup_name);
                     0
@@ -100,10 +164,37 @@ fn main() {
                 }
             }
         }
+        Some(("migrate", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            match record_operation(backup_name, "migrate", || migrate_backup(backup_name)) {
+                Ok(_) if dry_run => 0,
+                Ok((files, blobs)) => {
+                    info!(
+                        "Backup '{}' migrated: {} file(s) stored in {} blob(s).",
+                        backup_name, files, blobs
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error migrating backup: {}", e);
+                    1
+                }
+            }
+        }
         Some(("add", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
             let file_path = sub_matches.get_one::<String>("PATH").unwrap();
-            match add_to_backup(backup_name, file_path) {
+            let added =
+                record_operation(
+                    backup_name,
+                    &format!("add {file_path}"),
+                    || match sub_matches.get_flag("repo-relative") {
+                        true => add_repo_relative(backup_name, file_path),
+                        false => add_to_backup(backup_name, file_path),
+                    },
+                );
+            match added {
+                Ok(_) if dry_run => 0,
                 Ok(_) => {
                     info!("File '{}' added to backup '{}'.", file_path, backup_name);
                     0
@@ -114,60 +205,486 @@ fn main() {
                 }
             }
         }
-        Some(("restore", sub_matches)) => {
+        Some(("link", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
-            let copy = sub_matches.get_flag("copy");
-            let overwrite = sub_matches.contains_id("overwrite");
-            match restore_backup(backup_name, copy, overwrite) {
+            let existing_path = sub_matches.get_one::<String>("EXISTING_PATH").unwrap();
+            let new_path = sub_matches.get_one::<String>("NEW_PATH").unwrap();
+            let command = format!("link {existing_path} {new_path}");
+            match record_operation(backup_name, &command, || {
+                link_file(backup_name, existing_path, new_path)
+            }) {
+                Ok(_) if dry_run => 0,
                 Ok(_) => {
-                    info!("Backup '{}' restored successfully.", backup_name);
+                    info!("File '{}' linked to '{}'.", new_path, existing_path);
                     0
                 }
                 Err(e) => {
-                    eprintln!("Error restoring backup: {}", e);
+                    eprintln!("Error linking file: {}", e);
                     1
                 }
             }
         }
-        Some(("rm", sub_matches)) => {
+        Some(("unlink", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
-            let file_path = sub_matches.get_one::<String>("PATH");
-            let delete = sub_matches.get_flag("delete");
-            let remove = || match file_path {
-                Some(f) => match remove_from_backup(backup_name, f.as_str(), delete) {
-                    Ok(_) => 0,
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            match record_operation(backup_name, &format!("unlink {file_path}"), || {
+                unlink_file(backup_name, file_path)
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!(
+                        "File '{}' unlinked from backup '{}'.",
+                        file_path, backup_name
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error unlinking file: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("mode", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let mode = *sub_matches.get_one::<LinkMode>("MODE").unwrap();
+            match record_operation(backup_name, &format!("mode {file_path} {mode}"), || {
+                set_link_mode(backup_name, file_path, mode)
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!("File '{}' is now placed as a {}.", file_path, mode);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error setting link mode: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("edit", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            match record_operation(backup_name, &format!("edit {file_path}"), || {
+                edit_backup_file(backup_name, file_path)
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!("File '{}' updated in backup '{}'.", file_path, backup_name);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error editing file: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("get", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let key = sub_matches.get_one::<String>("KEY").unwrap();
+            let format = sub_matches.get_one::<Format>("format").copied();
+            match get_key(backup_name, file_path, key, format) {
+                Ok(value) => {
+                    println!("{value}");
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error getting key: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("set", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let key = sub_matches.get_one::<String>("KEY").unwrap();
+            let format = sub_matches.get_one::<Format>("format").copied();
+            let value = match sub_matches.get_one::<String>("VALUE") {
+                Some(value) => Ok(value.clone()),
+                None => {
+                    // Read the value from stdin, without the final newline:
+                    let mut value = String::new();
+                    io::stdin().read_to_string(&mut value).map(|_| {
+                        let value = value.strip_suffix('\n').unwrap_or(&value);
+                        value.strip_suffix('\r').unwrap_or(value).to_string()
+                    })
+                }
+            };
+            let set = value.and_then(|value| {
+                record_operation(backup_name, &format!("set {file_path} {key}"), || {
+                    set_key(backup_name, file_path, key, &value, format)
+                })
+            });
+            match set {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!("Key '{}' set in '{}'.", key, file_path);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error setting key: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("unset", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let key = sub_matches.get_one::<String>("KEY").unwrap();
+            let format = sub_matches.get_one::<Format>("format").copied();
+            match record_operation(backup_name, &format!("unset {file_path} {key}"), || {
+                unset_key(backup_name, file_path, key, format)
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!("Key '{}' removed from '{}'.", key, file_path);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error unsetting key: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("run", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let files: Vec<String> = sub_matches
+                .get_many::<String>("file")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            let command: Vec<String> = sub_matches
+                .get_many::<String>("COMMAND")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            match run_with_env(backup_name, &files, &command) {
+                Ok(_) => 0,
+                Err(e) => {
+                    eprintln!("Error running command: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("env", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let shell = sub_matches
+                .get_one::<EnvShell>("shell")
+                .copied()
+                .unwrap_or_else(EnvShell::detect);
+            let unset = sub_matches.get_flag("unset");
+            match env_statements(backup_name, file_path, shell, unset) {
+                Ok(statements) => {
+                    for statement in statements {
+                        println!("{statement}");
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error loading environment: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("render", sub_matches)) => {
+            let template = sub_matches.get_one::<String>("TEMPLATE").unwrap();
+            let output = sub_matches.get_one::<String>("output");
+            let add = sub_matches.get_one::<String>("add");
+            match render_template(
+                template,
+                output.map(|s| s.as_str()),
+                add.map(|s| s.as_str()),
+            ) {
+                Ok(Some(rendered)) => {
+                    print!("{rendered}");
+                    0
+                }
+                Ok(None) if dry_run => 0,
+                Ok(None) => {
+                    info!("Template '{}' rendered to '{}'.", template, output.unwrap());
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error rendering template: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("generate", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let key = sub_matches.get_one::<String>("KEY").unwrap();
+            let length = *sub_matches.get_one::<usize>("length").unwrap();
+            let charset = sub_matches.get_one::<String>("charset").unwrap();
+            let if_missing = sub_matches.get_flag("if-missing");
+            match record_operation(backup_name, &format!("generate {file_path} {key}"), || {
+                generate_secret(backup_name, file_path, key, length, charset, if_missing)
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(true) => {
+                    info!("Generated a new value for '{}' in '{}'.", key, file_path);
+                    0
+                }
+                Ok(false) => {
+                    info!("Key '{}' already has a value in '{}'.", key, file_path);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error generating secret: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("example", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let output = sub_matches.get_one::<String>("output").map(|s| s.as_str());
+            if sub_matches.get_flag("check") {
+                match check_example(backup_name, file_path, output) {
+                    Ok(_) => {
+                        info!("Example file is up to date.");
+                        0
+                    }
                     Err(e) => {
-                        eprintln!("Error removing file from backup: {}", e);
+                        eprintln!("{}", e);
                         1
                     }
-                },
-                None => {
-                    eprintln!("File does not exist: {file_path:?}");
+                }
+            } else {
+                let hints = sub_matches.get_flag("hints");
+                match write_example(backup_name, file_path, output, hints) {
+                    Ok(_) if dry_run => 0,
+                    Ok(example) => {
+                        info!("Example file written: {}", example.display());
+                        0
+                    }
+                    Err(e) => {
+                        eprintln!("Error writing example file: {}", e);
+                        1
+                    }
+                }
+            }
+        }
+        Some(("schema", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let file_path = sub_matches.get_one::<String>("PATH").unwrap();
+            let schema_file = sub_matches.get_one::<String>("SCHEMA_FILE");
+            match record_operation(backup_name, &format!("schema {file_path}"), || {
+                set_schema(backup_name, file_path, schema_file.map(|s| s.as_str()))
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(_) if schema_file.is_some() => {
+                    info!("Schema set for '{}'.", file_path);
+                    0
+                }
+                Ok(_) => {
+                    info!("Schema removed from '{}'.", file_path);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error setting schema: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("check", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME");
+            match check_backups(backup_name.map(|s| s.as_str())) {
+                Ok(checked) => {
+                    info!("{} file(s) match their schema.", checked);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            }
+        }
+        Some(("policy", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let mut update = PolicyUpdate::default();
+            if let Some(max_age) = sub_matches.get_one::<u64>("max-age") {
+                update.max_age_days = Some(Some(*max_age));
+            } else if sub_matches.get_flag("no-max-age") {
+                update.max_age_days = Some(None);
+            }
+            if sub_matches.get_flag("relative-links") {
+                update.relative_links = Some(true);
+            } else if sub_matches.get_flag("absolute-links") {
+                update.relative_links = Some(false);
+            }
+            match record_operation(backup_name, "policy", || set_policy(backup_name, update)) {
+                Ok(_) if dry_run => 0,
+                Ok(_) => {
+                    info!("Policy updated for backup '{}'.", backup_name);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error setting policy: {}", e);
                     1
                 }
+            }
+        }
+        Some(("stale", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME");
+            let max_age = sub_matches.get_one::<u64>("max-age").copied();
+            let output_as_json = sub_matches.get_flag("json");
+            match report_stale(backup_name.map(|s| s.as_str()), max_age, output_as_json) {
+                Ok(false) => {
+                    info!("No stale secrets found.");
+                    0
+                }
+                Ok(true) => 1,
+                Err(e) => {
+                    eprintln!("{e}");
+                    1
+                }
+            }
+        }
+        Some(("sync", _)) => match sync_project() {
+            Ok(true) => {
+                info!("All declared files are in sync.");
+                0
+            }
+            Ok(false) => 1,
+            Err(e) => {
+                eprintln!("Error syncing project: {}", e);
+                1
+            }
+        },
+        Some(("sync-copies", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME");
+            match sync_copies(backup_name.map(|s| s.as_str())) {
+                Ok(true) => {
+                    info!("All copies are in sync.");
+                    0
+                }
+                Ok(false) => 1,
+                Err(e) => {
+                    eprintln!("Error syncing copies: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("restore", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let mode = [
+                ("copy", LinkMode::Copy),
+                ("hardlink", LinkMode::Hardlink),
+                ("symlink", LinkMode::Symlink),
+            ]
+            .into_iter()
+            .find(|(flag, _)| sub_matches.get_flag(flag))
+            .map(|(_, mode)| mode);
+            let on_conflict = match sub_matches.get_flag("overwrite") {
+                true => ConflictPolicy::Overwrite,
+                false => sub_matches
+                    .get_one::<ConflictPolicy>("on-conflict")
+                    .copied()
+                    .unwrap_or_default(),
             };
-            if delete {
-                match confirm(ConfirmProps {
-                    message: "Do you want to permanently delete this file AND its backup?"
-                        .to_string(),
-                    help: file_path.cloned(),
-                    ..Default::default()
-                }) {
-                    Ok(true) => {
-                        debug!("hioi");
-                        remove()
-                    }
-                    _ => 1,
+            let here = sub_matches.get_flag("here");
+            let root = sub_matches.get_one::<String>("root").map(|s| s.as_str());
+            let output_as_json = sub_matches.get_flag("json");
+            let report = Selector::from_args(sub_matches).and_then(|selector| {
+                record_operation(backup_name, "restore", || match here {
+                    true => restore_here(backup_name, &selector, mode, on_conflict),
+                    false => restore_backup(backup_name, &selector, mode, on_conflict, root),
+                })
+            });
+            match report {
+                Ok(report) => {
+                    report.print(output_as_json);
+                    match &report.error {
+                        Some(e) => {
+                            eprintln!("Error restoring backup: {}", e);
+                            1
+                        }
+                        None if dry_run => 0,
+                        None => {
+                            match (here, root) {
+                                (true, _) => info!(
+                                    "{} file(s) restored from backup '{}' into this repository.",
+                                    report.restored(),
+                                    backup_name
+                                ),
+                                (false, Some(root)) => {
+                                    info!("Backup '{}' restored into {}.", backup_name, root)
+                                }
+                                (false, None) => {
+                                    info!("Backup '{}' restored successfully.", backup_name)
+                                }
+                            }
+                            0
+                        }
+                    }
+                }
+                Err(e) => {
+                    if output_as_json {
+                        RestoreReport::failed(backup_name, e.to_string()).print(true);
+                    }
+                    eprintln!("Error restoring backup: {}", e);
+                    1
+                }
+            }
+        }
+        Some(("rm", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let delete = sub_matches.get_flag("delete");
+            let files = Selector::from_args(sub_matches)
+                .and_then(|selector| select_files(backup_name, &selector));
+            let remove = |files: &[String]| {
+                let command = format!("rm {}", files.join(" "));
+                let removed = record_operation(backup_name, &command, || {
+                    let mut exit_code = 0;
+                    for f in files {
+                        if let Err(e) = remove_from_backup(backup_name, f.as_str(), delete) {
+                            eprintln!("Error removing file from backup: {}", e);
+                            exit_code = 1;
+                        }
+                    }
+                    Ok(exit_code)
+                });
+                removed.unwrap_or_else(|e| {
+                    eprintln!("Error recording the history of the backup: {}", e);
+                    1
+                })
+            };
+            match files {
+                Err(e) => {
+                    eprintln!("Error removing file from backup: {}", e);
+                    1
+                }
+                Ok(files) if delete && !dry_run => {
+                    match confirm(ConfirmProps {
+                        message: "Do you want to permanently delete this file AND its backup?"
+                            .to_string(),
+                        help: Some(format!(
+                            "{} (until `{} history {backup_name} --clear`, the history keeps a copy, for undo)",
+                            files.join(", "),
+                            env!("CARGO_BIN_NAME")
+                        )),
+                        ..Default::default()
+                    }) {
+                        Ok(true) => {
+                            debug!("hioi");
+                            remove(&files)
+                        }
+                        _ => 1,
+                    }
                 }
-            } else {
                 //Remove without confirmation:
-                remove()
+                Ok(files) => remove(&files),
             }
         }
         Some(("ls", sub_matches)) => {
             let output_as_json = sub_matches.get_flag("json");
             if let Some(backup_name) = sub_matches.get_one::<String>("BACKUP_NAME") {
-                match list_backup_files(backup_name, output_as_json) {
+                match Selector::from_args(sub_matches)
+                    .and_then(|selector| list_backup_files(backup_name, &selector, output_as_json))
+                {
                     Err(e) => {
                         eprintln!("{e}");
                         1
@@ -184,6 +701,104 @@ fn main() {
                 }
             }
         }
+        Some(("tag", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let tag = sub_matches.get_one::<String>("TAG").unwrap();
+            let remove = sub_matches.get_flag("remove");
+            let command = match remove {
+                true => format!("tag --remove {tag}"),
+                false => format!("tag {tag}"),
+            };
+            match Selector::from_args(sub_matches).and_then(|selector| {
+                record_operation(backup_name, &command, || {
+                    tag_files(backup_name, tag, &selector, remove)
+                })
+            }) {
+                Ok(_) if dry_run => 0,
+                Ok(n) => {
+                    match remove {
+                        true => info!("{n} file(s) untagged '{tag}'."),
+                        false => info!("{n} file(s) tagged '{tag}'."),
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error tagging files: {e}");
+                    1
+                }
+            }
+        }
+        Some(("history", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let output_as_json = sub_matches.get_flag("json");
+            let keep = match sub_matches.get_flag("clear") {
+                true => Some(0),
+                false => sub_matches.get_one::<usize>("keep").copied(),
+            };
+            match keep {
+                Some(keep) => match prune_history(backup_name, keep) {
+                    Ok(_) if dry_run => 0,
+                    Ok(n) => {
+                        info!("{n} operation(s) forgotten from the history of backup '{backup_name}'.");
+                        0
+                    }
+                    Err(e) => {
+                        eprintln!("Error pruning the history: {e}");
+                        1
+                    }
+                },
+                None => match print_history(backup_name, output_as_json) {
+                    Ok(_) => 0,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        1
+                    }
+                },
+            }
+        }
+        Some(("undo", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let count = *sub_matches.get_one::<usize>("N").unwrap();
+            match undo_operations(backup_name, count) {
+                Ok(_) if dry_run => 0,
+                Ok(undone) => {
+                    for command in undone {
+                        info!("Undone: {command}");
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Error undoing: {e}");
+                    1
+                }
+            }
+        }
+        Some(("audit-log", sub_matches)) => match sub_matches.subcommand() {
+            Some(("verify", verify_matches)) => {
+                let backup_name = verify_matches.get_one::<String>("BACKUP_NAME").unwrap();
+                let last_hash = verify_matches.get_one::<String>("last-hash");
+                match verify_audit_log(backup_name, last_hash.map(|s| s.as_str())) {
+                    Ok(true) => 0,
+                    Ok(false) => 1,
+                    Err(e) => {
+                        eprintln!("Error verifying audit log: {e}");
+                        1
+                    }
+                }
+            }
+            _ => 1,
+        },
+        Some(("status", sub_matches)) => {
+            let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
+            let output_as_json = sub_matches.get_flag("json");
+            match backup_status(backup_name, output_as_json) {
+                Ok(_) => 0,
+                Err(e) => {
+                    eprintln!("{e}");
+                    1
+                }
+            }
+        }
         Some(("commit", sub_matches)) => {
             let backup_name = sub_matches.get_one::<String>("BACKUP_NAME").unwrap();
             info!("Commit backup '{}' (not implemented).", backup_name);
@@ -195,13 +810,8 @@ fn main() {
             0
         }
         Some(("completions", sub_matches)) => {
-            if let Some(shell) = sub_matches.get_one::<String>("shell") {
-                match shell.as_str() {
-                    "bash" => generate_completion_script(Shell::Bash),
-                    "zsh" => generate_completion_script(Shell::Zsh),
-                    "fish" => generate_completion_script(Shell::Fish),
-                    shell => eprintln!("Unsupported shell: {shell}"),
-                }
+            if let Some(shell) = sub_matches.get_one::<Shell>("shell") {
+                generate_completion_script(*shell);
                 0
             } else {
                 eprintln!(
@@ -232,6 +842,16 @@ fn main() {
         }
         _ => 1,
     };
+    let exit_code = match audit.map(|audit| audit.finish(exit_code == 0)) {
+        Some(Err(e)) => {
+            eprintln!("Error writing the audit log: {e}");
+            1
+        }
+        _ => exit_code,
+    };
+    if dry_run && exit_code == 0 {
+        info!("Dry run: nothing was changed.");
+    }
 
     eprintln!("");
     std::process::exit(exit_code);
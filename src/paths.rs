@@ -2,13 +2,17 @@
 use crate::prelude::*;
 
 use crate::config::load_config;
+use crate::dry_run::{manifest_exists, pending, pretend_write};
+use crate::get_options;
+use crate::schema::Schema;
 use base64::engine::general_purpose::URL_SAFE;
 use base64::Engine;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::env;
-use std::fs::{canonicalize, File, OpenOptions};
+use std::fmt;
+use std::fs::{self, canonicalize, File, OpenOptions};
 use std::io;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -16,6 +20,232 @@ use std::path::{Path, PathBuf};
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Paths {
     pub files: IndexMap<String, String>, // original_path -> backup_path
+    #[serde(default)]
+    pub meta: IndexMap<String, FileMeta>, // original_path -> metadata
+    #[serde(default)]
+    pub max_age_days: Option<u64>, // the rotation policy for every secret
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub blobs: IndexMap<String, usize>, // blob id -> reference count (Content layout only)
+    #[serde(default)]
+    pub relative_links: bool, // create relative symlinks to the backup files
+}
+
+/// How the backup files are named.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// One backup file per original path, named by the hash of the path.
+    #[default]
+    PathHash,
+    /// Backup files (blobs) are shared by every path with the same
+    /// contents, and are named by the hash of their contents. A blob is
+    /// never changed: an edited entry moves to the blob for its new
+    /// contents, together with its aliases, but not with the other
+    /// entries that merely had the same contents.
+    Content,
+}
+
+/// Optional metadata kept for each file in the backup.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct FileMeta {
+    pub schema: Option<Schema>,
+    pub changed: Option<u64>, // unix time the contents last changed
+    pub keys_changed: IndexMap<String, u64>, // dotenv key -> unix time last changed
+    pub alias_of: Option<String>, // the original path this is an extra link of
+    pub repo: Option<RepoPath>,
+    pub mode: LinkMode,            // how the backup file is placed at this path
+    pub copy_hash: Option<String>, // content hash of a copy, when it was last in sync
+    pub tags: Vec<String>,
+}
+
+/// How the backup file is placed at its original path.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum LinkMode {
+    /// A symlink to the backup file.
+    #[default]
+    Symlink,
+    /// A hard link to the backup file (on the same filesystem).
+    Hardlink,
+    /// A copy of the backup file, which hushcrumbs keeps track of.
+    Copy,
+}
+
+impl fmt::Display for LinkMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkMode::Symlink => write!(f, "symlink"),
+            LinkMode::Hardlink => write!(f, "hardlink"),
+            LinkMode::Copy => write!(f, "copy"),
+        }
+    }
+}
+
+impl LinkMode {
+    pub fn parse(s: &str) -> Option<LinkMode> {
+        match s {
+            "symlink" => Some(LinkMode::Symlink),
+            "hardlink" => Some(LinkMode::Hardlink),
+            "copy" => Some(LinkMode::Copy),
+            _ => None,
+        }
+    }
+}
+
+/// Where a file lives within a git repository, in any clone of it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RepoPath {
+    pub remote: String, // the normalized remote URL
+    pub path: String,   // relative to the root of the repository
+}
+
+impl Paths {
+    /// Should new symlinks to the backup files be relative?
+    pub fn wants_relative_links(&self) -> bool {
+        self.relative_links || get_options().relative
+    }
+
+    /// Create a symlink at `link` to a file in the backup. A relative
+    /// symlink is computed from the parent directory of the link, so it
+    /// still resolves when both are moved (or mounted) together.
+    pub fn symlink(&self, backup_file: &Path, link: &Path) -> io::Result<()> {
+        let target = if self.wants_relative_links() {
+            let dir = canonicalize(link.parent().expect("failed to get parent directory"))?;
+            relative_path(&dir, &canonicalize(backup_file)?)
+        } else {
+            backup_file.to_path_buf()
+        };
+        debug!("symlink: {link:?} -> {target:?}");
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    /// Add (or replace) a file entry, counting the references to its blob.
+    pub fn insert(&mut self, original_path: &str, id: &str) {
+        if let Some(old) = self.files.insert(original_path.to_string(), id.to_string()) {
+            self.release(&old);
+        }
+        if self.layout == Layout::Content {
+            *self.blobs.entry(id.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Add an alias: another path linked to the same backup file.
+    pub fn insert_alias(&mut self, original_path: &str, alias_path: &str) {
+        let primary = self.primary(original_path).to_string();
+        let id = self.files[&primary].clone();
+        self.insert(alias_path, &id);
+        // (it may already have been placed in some mode)
+        self.meta
+            .entry(alias_path.to_string())
+            .or_default()
+            .alias_of = Some(primary);
+    }
+
+    /// The path that holds the metadata of an entry (itself, unless it is an alias).
+    pub fn primary<'a>(&'a self, original_path: &'a str) -> &'a str {
+        match self
+            .meta
+            .get(original_path)
+            .and_then(|m| m.alias_of.as_deref())
+        {
+            Some(primary) => primary,
+            None => original_path,
+        }
+    }
+
+    /// How the backup file is placed at the given path.
+    pub fn mode(&self, original_path: &str) -> LinkMode {
+        self.meta
+            .get(original_path)
+            .map(|m| m.mode)
+            .unwrap_or_default()
+    }
+
+    /// Set how the backup file is placed at the given path.
+    pub fn set_mode(&mut self, original_path: &str, mode: LinkMode) {
+        if mode != self.mode(original_path) {
+            self.meta.entry(original_path.to_string()).or_default().mode = mode;
+        }
+    }
+
+    /// The aliases of an entry.
+    pub fn aliases(&self, original_path: &str) -> Vec<String> {
+        self.meta
+            .iter()
+            .filter(|(_, m)| m.alias_of.as_deref() == Some(original_path))
+            .map(|(alias, _)| alias.clone())
+            .collect()
+    }
+
+    /// Remove a file entry, and all of its metadata. If the entry has
+    /// aliases, the first of them takes over its metadata. Returns the id
+    /// of the backup file, if nothing else refers to it any more.
+    pub fn remove(&mut self, original_path: &str) -> Option<String> {
+        let meta = self.meta.shift_remove(original_path);
+        if let Some((first, rest)) = self.aliases(original_path).split_first() {
+            // (but each path keeps its own link mode and tags)
+            let own = self.meta.shift_remove(first).unwrap_or_default();
+            let meta = meta.filter(|m| m.alias_of.is_none()).unwrap_or_default();
+            self.meta.insert(
+                first.clone(),
+                FileMeta {
+                    mode: own.mode,
+                    copy_hash: own.copy_hash,
+                    tags: own.tags,
+                    ..meta
+                },
+            );
+            for alias in rest {
+                self.meta.entry(alias.clone()).or_default().alias_of = Some(first.clone());
+            }
+        }
+        let id = self.files.shift_remove(original_path)?;
+        self.release(&id).then_some(id)
+    }
+
+    /// Drop a reference to a backup file. Returns true if it is unused.
+    fn release(&mut self, id: &str) -> bool {
+        if let Some(count) = self.blobs.get_mut(id) {
+            *count -= 1;
+            if *count == 0 {
+                self.blobs.shift_remove(id);
+            }
+        }
+        // Other entries may still be linked to the same file:
+        !self.files.values().any(|v| v == id)
+    }
+
+    /// The entries that share the backup file of an entry on purpose:
+    /// the entry (or the one it is an alias of) and its aliases.
+    pub fn linked_entries(&self, original_path: &str) -> Vec<String> {
+        let primary = self.primary(original_path).to_string();
+        let mut entries = self.aliases(&primary);
+        entries.insert(0, primary);
+        entries
+    }
+
+    /// Find the blob for these contents, named by their hash. Returns the
+    /// id, and whether the blob already exists.
+    pub fn find_blob(&self, backup_dir: &Path, contents: &[u8]) -> (String, bool) {
+        let hash = content_hash(contents);
+        let mut id = hash.clone();
+        let mut n = 1;
+        loop {
+            let blob = backup_dir.join(&id);
+            if !blob.exists() {
+                return (id, false);
+            }
+            // (a file of that name may be left over from an interrupted
+            // change, or not be a blob at all, so check that it holds these
+            // contents before sharing it)
+            if self.blobs.contains_key(&id) && fs::read(&blob).is_ok_and(|c| c == contents) {
+                return (id, true);
+            }
+            id = format!("{hash}-{n}");
+            n += 1;
+        }
+    }
 }
 
 pub fn update_paths_ron(
@@ -32,9 +262,12 @@ pub fn update_paths_ron(
     let paths_file = Path::new(backup_dir).join("paths.ron");
 
     // Load or initialize the paths file
-    let mut paths: Paths = if paths_file.exists() {
-        let file = File::open(&paths_file)?;
-        match ron::de::from_reader(file) {
+    let mut paths: Paths = if manifest_exists(&paths_file) {
+        let parsed = match pending(&paths_file) {
+            Some(pending) => ron::de::from_str(&pending),
+            None => ron::de::from_reader(File::open(&paths_file)?),
+        };
+        match parsed {
             Ok(paths) => paths,
             Err(_) => {
                 return Err(io::Error::new(
@@ -48,17 +281,19 @@ pub fn update_paths_ron(
     };
 
     // Add the new entry to the paths file
-    paths.files.insert(
-        original_path.as_os_str().to_string_lossy().to_string(),
+    paths.insert(
+        &original_path.as_os_str().to_string_lossy(),
         new_path
             .file_name()
             .unwrap()
             .to_str()
-            .expect("invalid to_str conversion")
-            .to_string(),
+            .expect("invalid to_str conversion"),
     );
 
     // Write the updated paths back to the file
+    if pretend_write(&paths_file, &paths)? {
+        return Ok(());
+    }
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
@@ -135,12 +370,32 @@ pub fn reverse_files_map(files: &IndexMap<String, String>) -> IndexMap<String, S
 
 pub fn file_hash(s: &str) -> String {
     debug!("file_hash input: {s}");
-    let hash = URL_SAFE.encode(Sha256::digest(s.as_bytes())).to_string();
-    let hash = hash.trim_end_matches('=').to_string();
+    let hash = content_hash(s.as_bytes());
     debug!("file_hash: {hash}");
     hash
 }
 
+pub fn content_hash(contents: &[u8]) -> String {
+    let hash = URL_SAFE.encode(Sha256::digest(contents)).to_string();
+    hash.trim_end_matches('=').to_string()
+}
+
+/// The path to `target`, relative to the directory `from`.
+/// Both paths must be absolute.
+pub fn relative_path(from: &Path, target: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &target[common..] {
+        path.push(component);
+    }
+    path
+}
+
 /// Make relative path into absolute, even for imaginary paths.
 /// This does not resolve symlinks!
 /// For real paths/symlinks, use std::fs::canonicalize instead!
@@ -157,8 +412,12 @@ pub fn get_backup_paths(backup_name: &str) -> io::Result<Paths> {
         .get(backup_name)
         .ok_or(io::Error::new(io::ErrorKind::NotFound, "Backup not found"))?;
     let paths_file = Path::new(backup_dir).join("paths.ron");
-    let file = File::open(paths_file.clone())?;
-    let paths: Paths = match ron::de::from_reader(file) {
+    // (or the paths saved earlier in a dry run)
+    let parsed = match pending(&paths_file) {
+        Some(pending) => ron::de::from_str(&pending),
+        None => ron::de::from_reader(File::open(&paths_file)?),
+    };
+    let paths: Paths = match parsed {
         Ok(paths) => paths,
         Err(_) => {
             return Err(io::Error::new(
@@ -177,6 +436,9 @@ pub fn set_backup_paths(backup_name: &str, paths: Paths) -> io::Result<()> {
         .get(backup_name)
         .ok_or(io::Error::new(io::ErrorKind::NotFound, "Backup not found"))?;
     let paths_file = Path::new(backup_dir).join("paths.ron");
+    if pretend_write(&paths_file, &paths)? {
+        return Ok(());
+    }
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
@@ -187,6 +449,70 @@ pub fn set_backup_paths(backup_name: &str, paths: Paths) -> io::Result<()> {
     Ok(())
 }
 
+pub fn get_backup_dir(backup_name: &str) -> io::Result<PathBuf> {
+    let config = load_config()?;
+    let backup_dir = config
+        .backups
+        .get(backup_name)
+        .ok_or(io::Error::new(io::ErrorKind::NotFound, "Backup not found"))?;
+    Ok(PathBuf::from(backup_dir))
+}
+
+/// Resolve a user supplied path into the form used for the keys of paths.ron.
+/// The parent directory is canonicalized (when it exists), but the
+/// file itself is not, because it is usually a symlink into the backup.
+pub fn resolve_original_path(path: &str) -> PathBuf {
+    let path = absolute_path(
+        expand_tilde_path(path)
+            .expect("failed to expand tilde")
+            .to_str()
+            .expect("failed to_str on path"),
+    );
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => match canonicalize(parent) {
+            Ok(parent) => parent.join(name),
+            Err(_) => path,
+        },
+        _ => path,
+    }
+}
+
+/// Find the entry for the given original path in a backup.
+/// Returns the key used in paths.ron, and the backup file that holds
+/// the contents of the original path.
+pub fn get_backup_entry(backup_name: &str, original_path: &str) -> io::Result<(String, PathBuf)> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    // A backup without a paths.ron has simply not had any files added yet:
+    let paths = match get_backup_paths(backup_name) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Paths::default(),
+        paths => paths?,
+    };
+    let original = resolve_original_path(original_path);
+    let key = original.to_str().expect("failed to_str on path");
+    if let Some(id) = paths.files.get(key) {
+        return Ok((key.to_string(), backup_dir.join(id)));
+    }
+    // The path may have been given through some other route to the symlink:
+    if let Ok(target) = canonicalize(&original) {
+        if target.parent() == canonicalize(&backup_dir).ok().as_deref() {
+            if let Some(id) = target.file_name().and_then(|f| f.to_str()) {
+                if let Some(key) = reverse_files_map(&paths.files).get(id) {
+                    return Ok((key.clone(), backup_dir.join(id)));
+                }
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("File not found in backup: {original_path}"),
+    ))
+}
+
+/// Find the backup file that holds the contents of the given original path.
+pub fn get_backup_file(backup_name: &str, original_path: &str) -> io::Result<PathBuf> {
+    get_backup_entry(backup_name, original_path).map(|(_, backup_file)| backup_file)
+}
+
 pub fn check_if_file_exists_in_backup(backup_name: &str, original_path: &str) -> io::Result<bool> {
     let paths = get_backup_paths(backup_name)?;
     if paths.files.contains_key(
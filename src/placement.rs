@@ -0,0 +1,360 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::atomic::atomic_write;
+use crate::dry_run::{is_dry_run, pretend};
+use crate::history;
+use crate::paths::{
+    content_hash, get_backup_dir, get_backup_entry, get_backup_paths, set_backup_paths, Layout,
+    LinkMode, Paths,
+};
+use crate::transaction::Transaction;
+use serde::Serialize;
+use std::fs::{self, canonicalize};
+use std::io::{self, ErrorKind};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// The state of the original path of a backed up file.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    AbsoluteLink,
+    RelativeLink,
+    BrokenLink,
+    Hardlink,
+    StaleHardlink, // a regular file, no longer linked to the backup file
+    Copy,
+    DivergedCopy,  // a tracked copy whose contents differ from the backup
+    UntrackedCopy, // a regular file where a symlink is expected
+    Missing,
+    Conflict,
+}
+
+impl Placement {
+    /// Inspect the original path, for a file placed in the given mode.
+    pub fn of(mode: LinkMode, original: &Path, backup: &Path) -> Placement {
+        let metadata = match fs::symlink_metadata(original) {
+            Err(_) => return Placement::Missing,
+            Ok(m) => m,
+        };
+        match mode {
+            LinkMode::Symlink if metadata.is_symlink() => {
+                if canonicalize(original).ok() != canonicalize(backup).ok() {
+                    Placement::BrokenLink
+                } else if fs::read_link(original).is_ok_and(|t| t.is_relative()) {
+                    Placement::RelativeLink
+                } else {
+                    Placement::AbsoluteLink
+                }
+            }
+            LinkMode::Symlink if metadata.is_file() => Placement::UntrackedCopy,
+            LinkMode::Hardlink if metadata.is_file() => match fs::metadata(backup) {
+                Ok(b) if (b.dev(), b.ino()) == (metadata.dev(), metadata.ino()) => {
+                    Placement::Hardlink
+                }
+                _ => Placement::StaleHardlink,
+            },
+            LinkMode::Copy if metadata.is_file() => match (fs::read(original), fs::read(backup)) {
+                (Ok(a), Ok(b)) if a == b => Placement::Copy,
+                _ => Placement::DivergedCopy,
+            },
+            _ => Placement::Conflict,
+        }
+    }
+
+    /// Is the backup file correctly placed at the original path?
+    pub fn is_current(&self) -> bool {
+        matches!(
+            self,
+            Placement::AbsoluteLink
+                | Placement::RelativeLink
+                | Placement::Hardlink
+                | Placement::Copy
+        )
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Placement::AbsoluteLink => "linked",
+            Placement::RelativeLink => "linked (relative)",
+            Placement::BrokenLink => "broken link",
+            Placement::Hardlink => "hardlinked",
+            Placement::StaleHardlink => "stale hardlink",
+            Placement::Copy => "copy",
+            Placement::DivergedCopy => "copy (diverged)",
+            Placement::UntrackedCopy => "untracked copy",
+            Placement::Missing => "missing",
+            Placement::Conflict => "conflict",
+        }
+    }
+}
+
+/// Record that the backup file is placed at the path in the given mode.
+/// For a copy, the hash of its contents is recorded too, so that
+/// `sync-copies` can tell later which side has changed since.
+pub fn record_placement(
+    paths: &mut Paths,
+    original_path: &str,
+    mode: LinkMode,
+    backup: &Path,
+) -> io::Result<()> {
+    paths.set_mode(original_path, mode);
+    let copy_hash = match mode {
+        LinkMode::Copy => Some(content_hash(&fs::read(backup)?)),
+        _ => None,
+    };
+    match paths.meta.get_mut(original_path) {
+        Some(meta) => meta.copy_hash = copy_hash,
+        None if copy_hash.is_some() => {
+            paths
+                .meta
+                .entry(original_path.to_string())
+                .or_default()
+                .copy_hash = copy_hash
+        }
+        None => (),
+    }
+    Ok(())
+}
+
+/// Place the backup file at the original path, in the given mode,
+/// replacing whatever file or symlink is there now, and record it.
+pub fn place(paths: &mut Paths, mode: LinkMode, backup: &Path, original: &Path) -> io::Result<()> {
+    if mode == LinkMode::Hardlink {
+        // Check first, so that the existing file is not removed for nothing:
+        let dir = original.parent().expect("failed to get parent directory");
+        if fs::metadata(dir)?.dev() != fs::metadata(backup)?.dev() {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "Cannot hardlink {}: it is not on the same filesystem as the backup",
+                    original.display()
+                ),
+            ));
+        }
+    }
+    let placing = || {
+        format!(
+            "place {} at {} as a {mode}",
+            backup.display(),
+            original.display()
+        )
+    };
+    if !pretend(placing) {
+        if fs::symlink_metadata(original).is_ok() {
+            fs::remove_file(original)?;
+        }
+        debug!("placing {backup:?} at {original:?} as {mode:?}");
+        match mode {
+            LinkMode::Symlink => paths.symlink(backup, original)?,
+            LinkMode::Hardlink => fs::hard_link(backup, original)?,
+            LinkMode::Copy => fs::copy(backup, original).map(|_| ())?,
+        }
+    }
+    let original = original.to_str().expect("failed to_str on path");
+    record_placement(paths, original, mode, backup)
+}
+
+/// Link the hardlinks of the backup again where they have become regular
+/// files with the same contents (eg. after an undo wrote them back).
+pub fn relink_hardlinks(backup_name: &str) -> io::Result<()> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    let mut paths = match get_backup_paths(backup_name) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        paths => paths?,
+    };
+    let hardlinks: Vec<(String, String)> = paths
+        .files
+        .iter()
+        .filter(|(path, _)| paths.mode(path) == LinkMode::Hardlink)
+        .map(|(path, id)| (path.clone(), id.clone()))
+        .collect();
+    for (path, id) in hardlinks {
+        let (original, backup) = (Path::new(&path), backup_dir.join(id));
+        if Placement::of(LinkMode::Hardlink, original, &backup) == Placement::StaleHardlink
+            && fs::read(original)? == fs::read(&backup)?
+        {
+            place(&mut paths, LinkMode::Hardlink, &backup, original)?;
+        }
+    }
+    Ok(())
+}
+
+/// Place the backup file at the original path, as `place` does, so that
+/// the change can be undone (with the history of the current operation).
+pub fn place_undoable(
+    paths: &mut Paths,
+    mode: LinkMode,
+    backup: &Path,
+    original: &Path,
+) -> io::Result<()> {
+    if is_dry_run() {
+        return place(paths, mode, backup, original);
+    }
+    let mut tx = Transaction::new();
+    let result = (|| {
+        if fs::symlink_metadata(original).is_ok() {
+            tx.set_aside(original)?;
+        }
+        tx.create(original);
+        place(paths, mode, backup, original)
+    })();
+    match result {
+        Ok(()) => {
+            tx.commit()?.into_iter().for_each(history::record);
+            Ok(())
+        }
+        Err(e) => {
+            tx.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+/// Give the entry new contents in the backup, within the transaction.
+/// Returns the backup file that now holds them. A blob of a
+/// content-addressed backup is never changed: the entry and its aliases
+/// move to the blob for the new contents, and so do their symlinks,
+/// while any other entry keeps the old blob. (Hardlinks and copies are
+/// left to `refresh_placed`.)
+pub fn rewrite_blob(
+    paths: &mut Paths,
+    backup_dir: &Path,
+    key: &str,
+    contents: &[u8],
+    tx: &mut Transaction,
+) -> io::Result<PathBuf> {
+    let old_id = paths.files[key].clone();
+    let old_blob = backup_dir.join(&old_id);
+    if paths.layout != Layout::Content {
+        tx.write(&old_blob, contents)?;
+        return Ok(old_blob);
+    }
+    let (id, exists) = paths.find_blob(backup_dir, contents);
+    let blob = backup_dir.join(&id);
+    if id == old_id {
+        return Ok(blob);
+    }
+    if !exists {
+        tx.create(&blob);
+        atomic_write(&blob, contents)?;
+        fs::set_permissions(&blob, fs::metadata(&old_blob)?.permissions())?;
+    }
+    for entry in paths.linked_entries(key) {
+        if paths.files[&entry] != old_id {
+            continue;
+        }
+        let original = Path::new(&entry);
+        let mode = paths.mode(&entry);
+        if mode == LinkMode::Symlink && Placement::of(mode, original, &old_blob).is_current() {
+            tx.set_aside(original)?;
+            tx.create(original);
+            paths.symlink(&blob, original)?;
+        }
+        paths.insert(&entry, &id);
+    }
+    if !paths.files.values().any(|v| *v == old_id) {
+        tx.set_aside(&old_blob)?;
+    }
+    Ok(blob)
+}
+
+/// Replace the contents of the backup file of an entry, all or nothing.
+/// (See `rewrite_blob`.)
+pub fn write_backup_file(
+    backup_name: &str,
+    original_path: &str,
+    contents: &[u8],
+) -> io::Result<()> {
+    let (key, backup_file) = get_backup_entry(backup_name, original_path)?;
+    if pretend(|| format!("write {} (the backup of {key})", backup_file.display())) {
+        return Ok(());
+    }
+    let backup_dir = get_backup_dir(backup_name)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    let mut tx = Transaction::new();
+    let result = rewrite_blob(&mut paths, &backup_dir, &key, contents, &mut tx)
+        .and_then(|_| set_backup_paths(backup_name, paths));
+    match result {
+        Ok(()) => {
+            tx.commit()?.into_iter().for_each(history::record);
+            Ok(())
+        }
+        Err(e) => {
+            tx.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+/// After the backup file of an entry was rewritten, bring its hardlinks
+/// and tracked copies up to date. (A rewrite replaces the backup file, so
+/// hardlinks to it are left behind.) A copy is only updated if it still
+/// had the `old` contents, so local changes to it are never lost.
+pub fn refresh_placements(
+    backup_name: &str,
+    original_path: &str,
+    old: Option<&[u8]>,
+) -> io::Result<()> {
+    let (key, _) = get_backup_entry(backup_name, original_path)?;
+    let backup_dir = get_backup_dir(backup_name)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    if is_dry_run() {
+        // (the backup file is unchanged, so only its other placements can be told)
+        for entry in paths.linked_entries(&key) {
+            let mode = paths.mode(&entry);
+            if mode != LinkMode::Symlink {
+                pretend(|| format!("update the {mode} at {entry}"));
+            }
+        }
+        return Ok(());
+    }
+    let mut tx = Transaction::new();
+    let result = refresh_placed(&mut paths, &backup_dir, &key, old, &mut tx)
+        .and_then(|()| set_backup_paths(backup_name, paths));
+    match result {
+        Ok(()) => {
+            tx.commit()?.into_iter().for_each(history::record);
+            Ok(())
+        }
+        Err(e) => {
+            tx.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+/// Refresh the hardlinks and copies of the entry in the loaded paths,
+/// within the transaction.
+pub fn refresh_placed(
+    paths: &mut Paths,
+    backup_dir: &Path,
+    key: &str,
+    old: Option<&[u8]>,
+    tx: &mut Transaction,
+) -> io::Result<()> {
+    let backup = backup_dir.join(&paths.files[key]);
+    // (not every entry that shares a content-addressed blob is linked to it)
+    for path in &paths.linked_entries(key) {
+        let mode = paths.mode(path);
+        let original = Path::new(path);
+        let refresh = match Placement::of(mode, original, &backup) {
+            Placement::StaleHardlink => true,
+            Placement::DivergedCopy => {
+                let unchanged = old.is_some_and(|old| fs::read(original).is_ok_and(|c| c == old));
+                if !unchanged {
+                    warn!("{path} has local changes, so it was not updated from the backup.");
+                }
+                unchanged
+            }
+            _ => false,
+        };
+        if refresh {
+            tx.set_aside(original)?;
+            tx.create(original);
+            place(paths, mode, &backup, original)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The name of the project file, committed to a repository, which
+/// declares the secret files of the project without their values.
+pub const PROJECT_FILE: &str = ".hushcrumbs.ron";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Project {
+    pub files: IndexMap<String, String>, // relative path -> backup name
+}
+
+/// Find the project file in the directory, or in the closest parent directory.
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_FILE))
+        .find(|f| f.is_file())
+}
+
+pub fn load_project(path: &Path) -> io::Result<Project> {
+    let file = File::open(path)?;
+    let project: Project = ron::de::from_reader(file).map_err(|e| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse {}: {e}", path.display()),
+        )
+    })?;
+    debug!("Project loaded: {path:?}");
+    Ok(project)
+}
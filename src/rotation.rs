@@ -0,0 +1,75 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dotenv::{is_dotenv_file_name, DotEnv};
+use crate::paths::{get_backup_entry, get_backup_paths, set_backup_paths, Paths};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The current time, in seconds since the unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The keys whose values differ between the old and new dotenv text,
+/// and the keys that were removed. Without any old text, every key is new.
+fn diff_keys(old: Option<&[u8]>, new: &[u8]) -> (Vec<String>, Vec<String>) {
+    let parse = |bytes: &[u8]| {
+        std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| DotEnv::parse(text).ok())
+    };
+    let new = match parse(new) {
+        Some(new) => new,
+        None => return (Vec::new(), Vec::new()),
+    };
+    let old = old.and_then(parse).unwrap_or_default();
+    let changed = new
+        .entries()
+        .iter()
+        .filter(|e| old.get(&e.key) != Some(e.value.as_str()))
+        .map(|e| e.key.clone())
+        .collect();
+    let removed = old
+        .entries()
+        .iter()
+        .filter(|e| new.get(&e.key).is_none())
+        .map(|e| e.key.clone())
+        .collect();
+    (changed, removed)
+}
+
+/// Record in the backup manifest that the contents of a file changed
+/// just now. For dotenv files, the keys that changed are recorded too.
+pub fn record_change(
+    backup_name: &str,
+    original_path: &str,
+    old: Option<&[u8]>,
+    new: &[u8],
+) -> io::Result<()> {
+    let (key, _) = get_backup_entry(backup_name, original_path)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    note_change(&mut paths, &key, old, new);
+    set_backup_paths(backup_name, paths)
+}
+
+/// Record a change to the contents of the entry in the loaded paths.
+pub fn note_change(paths: &mut Paths, key: &str, old: Option<&[u8]>, new: &[u8]) {
+    let key = paths.primary(key).to_string();
+    let now = now();
+    let meta = paths.meta.entry(key.clone()).or_default();
+    meta.changed = Some(now);
+    if is_dotenv_file_name(&key) {
+        let (changed, removed) = diff_keys(old, new);
+        debug!("keys changed in {key}: {changed:?}, removed: {removed:?}");
+        for k in changed {
+            meta.keys_changed.insert(k, now);
+        }
+        for k in removed {
+            meta.keys_changed.shift_remove(&k);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dotenv::DotEnv;
+use crate::paths::{get_backup_entry, get_backup_paths, resolve_original_path};
+use indexmap::IndexMap;
+use regex::Regex;
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The rules that the keys of a dotenv file must follow.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Schema {
+    pub keys: IndexMap<String, KeyRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct KeyRule {
+    pub required: bool,
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+    pub pattern: Option<String>,
+    pub allowed: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Url,
+}
+
+impl ValueType {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueType::String => true,
+            ValueType::Integer => value.parse::<i64>().is_ok(),
+            ValueType::Number => value.parse::<f64>().is_ok(),
+            ValueType::Boolean => matches!(
+                value.to_lowercase().as_str(),
+                "true" | "false" | "yes" | "no" | "on" | "off" | "1" | "0"
+            ),
+            ValueType::Url => value
+                .split_once("://")
+                .is_some_and(|(scheme, rest)| !scheme.is_empty() && !rest.is_empty()),
+        }
+    }
+}
+
+impl Schema {
+    pub fn parse(text: &str) -> io::Result<Schema> {
+        // Allow `pattern: "..."` rather than `pattern: Some("...")`:
+        let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+        let schema: Schema = options.from_str(text).map_err(|e| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to parse schema: {e}"),
+            )
+        })?;
+        // Make sure the patterns are valid up front:
+        for (key, rule) in &schema.keys {
+            if let Some(pattern) = &rule.pattern {
+                Regex::new(pattern).map_err(|e| {
+                    io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid pattern for key {key}: {e}"),
+                    )
+                })?;
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Validate the dotenv file, returning a description of every problem.
+    pub fn validate(&self, dotenv: &DotEnv) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (key, rule) in &self.keys {
+            let value = match dotenv.get(key) {
+                Some(value) => value,
+                None => {
+                    if rule.required {
+                        problems.push(format!("{key}: required key is missing"));
+                    }
+                    continue;
+                }
+            };
+            if !rule.value_type.matches(value) {
+                problems.push(format!(
+                    "{key}: expected a value of type {:?}",
+                    rule.value_type
+                ));
+            }
+            if let Some(pattern) = &rule.pattern {
+                match Regex::new(pattern) {
+                    Ok(re) if re.is_match(value) => (),
+                    _ => problems.push(format!("{key}: does not match pattern {pattern:?}")),
+                }
+            }
+            if !rule.allowed.is_empty() && !rule.allowed.iter().any(|a| a == value) {
+                problems.push(format!("{key}: must be one of {:?}", rule.allowed));
+            }
+        }
+        problems
+    }
+}
+
+/// The repository local schema files for a dotenv file, in order of
+/// preference: `.env.local` -> `.env.local.schema`, then `.env.schema`.
+fn local_schema_paths(original: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(name) = original.file_name().and_then(|f| f.to_str()) {
+        paths.push(original.with_file_name(format!("{name}.schema")));
+    }
+    paths.push(original.with_file_name(".env.schema"));
+    paths
+}
+
+/// Find the schema for a file: the schema stored in the backup manifest
+/// takes precedence over a repository local schema file.
+pub fn find_schema(backup_name: &str, original_path: &str) -> io::Result<Option<Schema>> {
+    if let Ok((key, _)) = get_backup_entry(backup_name, original_path) {
+        let paths = get_backup_paths(backup_name)?;
+        let key = paths.primary(&key);
+        if let Some(schema) = paths.meta.get(key).and_then(|m| m.schema.clone()) {
+            debug!("using schema from manifest for {key}");
+            return Ok(Some(schema));
+        }
+    }
+    let original = resolve_original_path(original_path);
+    for path in local_schema_paths(&original) {
+        if path.is_file() {
+            debug!("using schema file: {path:?}");
+            return Schema::parse(&fs::read_to_string(&path)?).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Validate the new text of a dotenv file against its schema (if it has
+/// one), so that an invalid write can be rejected before it happens.
+pub fn validate_text(backup_name: &str, original_path: &str, text: &str) -> io::Result<()> {
+    let Some(schema) = find_schema(backup_name, original_path)? else {
+        return Ok(());
+    };
+    let problems = schema.validate(&DotEnv::parse(text)?);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{original_path} does not match its schema:\n  {}",
+                problems.join("\n  ")
+            ),
+        ))
+    }
+}
@@ -0,0 +1,162 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::paths::{absolute_path, expand_tilde_path, resolve_original_path, Paths};
+use clap::ArgMatches;
+use regex::Regex;
+use std::fs::canonicalize;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Chooses some of the files in a backup: by exact path (or everything
+/// in a directory), by glob, by tag, or by the directory they are under.
+/// Files must match one of the paths or globs (if any), one of the tags
+/// (if any), and be under the directory (if given).
+#[derive(Debug, Default)]
+pub struct Selector {
+    patterns: Vec<String>, // as given, eg. relative to the current directory
+    paths: Vec<PathBuf>,
+    globs: Vec<Regex>,
+    tags: Vec<String>,
+    under: Option<PathBuf>,
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Translate a glob into a regex: `*` and `?` don't match across
+/// directories, but `**` does.
+fn glob_to_regex(glob: &str) -> io::Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                match chars.next_if_eq(&'/') {
+                    Some(_) => regex.push_str("(?:.*/)?"),
+                    None => regex.push_str(".*"),
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    regex.push('^');
+                }
+                for c in chars.by_ref().take_while(|c| *c != ']') {
+                    match c {
+                        '-' => regex.push('-'), // (a range)
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, format!("Invalid glob {glob}: {e}")))
+}
+
+/// Make a glob absolute, canonicalizing the directories before the first
+/// wildcard the same way the keys of paths.ron are.
+fn resolve_glob(glob: &str) -> PathBuf {
+    let path = absolute_path(
+        expand_tilde_path(glob)
+            .expect("failed to expand tilde")
+            .to_str()
+            .expect("failed to_str on path"),
+    );
+    let components: Vec<_> = path.components().collect();
+    let fixed = components
+        .iter()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .count();
+    let prefix: PathBuf = components[..fixed].iter().collect();
+    let prefix = canonicalize(&prefix).unwrap_or(prefix);
+    prefix.join(components[fixed..].iter().collect::<PathBuf>())
+}
+
+impl Selector {
+    pub fn new(patterns: &[String], tags: &[String], under: Option<&str>) -> io::Result<Self> {
+        let mut selector = Selector {
+            patterns: patterns.to_vec(),
+            tags: tags.to_vec(),
+            under: under.map(|dir| canonicalize(dir).unwrap_or(resolve_original_path(dir))),
+            ..Default::default()
+        };
+        for pattern in patterns {
+            if is_glob(pattern) {
+                let glob = resolve_glob(pattern);
+                selector.globs.push(glob_to_regex(&glob.to_string_lossy())?);
+            } else {
+                selector.paths.push(resolve_original_path(pattern));
+            }
+        }
+        Ok(selector)
+    }
+
+    /// Build a selector from the `PATH`, `--tag` and `--under` arguments.
+    pub fn from_args(matches: &ArgMatches) -> io::Result<Self> {
+        let strings = |id| -> Vec<String> {
+            matches
+                .get_many::<String>(id)
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default()
+        };
+        Selector::new(
+            &strings("PATH"),
+            &strings("tag"),
+            matches.get_one::<String>("under").map(|s| s.as_str()),
+        )
+    }
+
+    /// Does it select every file?
+    pub fn is_all(&self) -> bool {
+        self.patterns.is_empty() && self.tags.is_empty() && self.under.is_none()
+    }
+
+    /// The paths as given, if the selector names nothing but exact files.
+    pub fn exact_paths(&self) -> Option<&[String]> {
+        let exact = self.globs.is_empty()
+            && self.tags.is_empty()
+            && self.under.is_none()
+            && !self.paths.iter().any(|p| p.is_dir());
+        exact.then_some(self.patterns.as_slice())
+    }
+
+    /// Does it select the entry `key` of the backup?
+    pub fn matches(&self, paths: &Paths, key: &str) -> bool {
+        self.matches_at(paths, key, Path::new(key))
+    }
+
+    /// Does it select the entry `key` of the backup, placed at `path`?
+    pub fn matches_at(&self, paths: &Paths, key: &str, path: &Path) -> bool {
+        let by_pattern = self.patterns.is_empty()
+            || self.paths.iter().any(|p| path.starts_with(p))
+            || self
+                .globs
+                .iter()
+                .any(|g| g.is_match(&path.to_string_lossy()));
+        let by_tag = self.tags.is_empty()
+            || paths
+                .meta
+                .get(key)
+                .is_some_and(|m| m.tags.iter().any(|t| self.tags.contains(t)));
+        let by_dir = self.under.as_ref().is_none_or(|dir| path.starts_with(dir));
+        by_pattern && by_tag && by_dir
+    }
+
+    /// The keys of the selected entries of the backup.
+    pub fn select<'a>(&self, paths: &'a Paths) -> Vec<&'a String> {
+        paths
+            .files
+            .keys()
+            .filter(|key| self.matches(paths, key))
+            .collect()
+    }
+}
@@ -1,5 +1,23 @@
 pub mod add;
+pub mod audit;
+pub mod check;
+pub mod copies;
+pub mod edit;
+pub mod env;
+pub mod example;
+pub mod generate;
+pub mod history;
 pub mod init;
+pub mod key;
+pub mod link;
 pub mod list;
+pub mod migrate;
+pub mod policy;
 pub mod remove;
+pub mod render;
 pub mod restore;
+pub mod run;
+pub mod stale;
+pub mod status;
+pub mod sync;
+pub mod tag;
@@ -2,10 +2,17 @@
 use crate::prelude::*;
 
 use crate::config::load_config;
-use crate::paths::{check_if_file_exists_in_backup, file_hash, update_paths_ron};
-use std::fs::{canonicalize, copy, remove_file, symlink_metadata};
+use crate::dotenv::is_dotenv_file_name;
+use crate::dry_run;
+use crate::git::repo_path;
+use crate::paths::{
+    check_if_file_exists_in_backup, file_hash, get_backup_paths, set_backup_paths,
+    update_paths_ron, Layout, Paths,
+};
+use crate::rotation::record_change;
+use crate::schema::validate_text;
+use std::fs::{canonicalize, read, read_to_string, symlink_metadata};
 use std::io;
-use std::os::unix::fs::symlink;
 use std::path::Path;
 
 pub fn add_to_backup(backup_name: &str, original_path: &str) -> io::Result<()> {
@@ -34,24 +41,55 @@ pub fn add_to_backup(backup_name: &str, original_path: &str) -> io::Result<()> {
     ))?;
     debug!("backup found");
 
-    // Hash the original path to form the backup file id:
-    let new_path = Path::new(backup_dir).join(file_hash(
-        absolute_path.to_str().expect("failed to_str on path"),
-    ));
-    debug!("new_path: {new_path:?}");
-    debug!("file_path: {file_path:?}");
     let metadata = symlink_metadata(original_path)?;
     debug!("metadata loaded");
     if metadata.is_symlink() {
         return Err(io::Error::new(io::ErrorKind::Other, "Cannot add symlink"));
     }
+    if is_dotenv_file_name(original_path) {
+        validate_text(backup_name, original_path, &read_to_string(original_path)?)?;
+    }
 
-    copy(original_path, &new_path)?;
-    remove_file(original_path)?;
+    let paths = match get_backup_paths(backup_name) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Paths::default(),
+        paths => paths?,
+    };
+    let contents = read(original_path)?;
+    let (id, exists) = match paths.layout {
+        // Hash the original path to form the backup file id:
+        Layout::PathHash => (
+            file_hash(absolute_path.to_str().expect("failed to_str on path")),
+            false,
+        ),
+        // Share the blob of any other file with the same contents:
+        Layout::Content => paths.find_blob(Path::new(backup_dir), &contents),
+    };
+    let new_path = Path::new(backup_dir).join(id);
+    debug!("new_path: {new_path:?}");
+    debug!("file_path: {file_path:?}");
+
+    if !exists {
+        dry_run::copy(Path::new(original_path), &new_path)?;
+    }
+    dry_run::remove_file(Path::new(original_path))?;
     debug!("moved");
-    symlink(&new_path, absolute_path.clone())?;
+    dry_run::symlink(&paths, &new_path, &absolute_path)?;
     debug!("symlinked");
 
     // Update paths.ron with the original path
-    update_paths_ron(backup_name, Path::new(&file_path.clone()), &new_path)
+    update_paths_ron(backup_name, Path::new(&file_path.clone()), &new_path)?;
+    record_change(backup_name, &file_path, None, &contents)
+}
+
+/// Add a file to the backup, and record where it is within its git
+/// repository, so that it can be restored into any clone of it.
+pub fn add_repo_relative(backup_name: &str, original_path: &str) -> io::Result<()> {
+    let absolute_path = canonicalize(original_path)?;
+    let repo = repo_path(&absolute_path)?;
+    debug!("repo path: {repo:?}");
+    add_to_backup(backup_name, original_path)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    let key = absolute_path.to_str().expect("failed to_str on path");
+    paths.meta.entry(key.to_string()).or_default().repo = Some(repo);
+    set_backup_paths(backup_name, paths)
 }
@@ -0,0 +1,29 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::audit::verify_log;
+use std::io;
+
+/// Verify the audit log of the backup, and report any tampering.
+/// Returns whether the log is intact.
+pub fn verify_audit_log(backup_name: &str, last_hash: Option<&str>) -> io::Result<bool> {
+    let verification = verify_log(backup_name, last_hash)?;
+    for problem in &verification.problems {
+        eprintln!("{problem}");
+    }
+    if !verification.problems.is_empty() {
+        eprintln!(
+            "The audit log of backup '{backup_name}' has been tampered with: {} problem(s) found.",
+            verification.problems.len()
+        );
+        return Ok(false);
+    }
+    match verification.last_hash {
+        Some(hash) => info!(
+            "The audit log of backup '{backup_name}' is intact: {} record(s), last hash {hash}",
+            verification.records
+        ),
+        None => info!("The audit log of backup '{backup_name}' is empty."),
+    }
+    Ok(true)
+}
@@ -0,0 +1,83 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::config::load_config;
+use crate::dotenv::{is_dotenv_file_name, DotEnv};
+use crate::paths::{get_backup_dir, get_backup_entry, get_backup_paths, set_backup_paths};
+use crate::schema::{find_schema, Schema};
+use std::fs;
+use std::io::{self, ErrorKind};
+
+/// Store a schema for a tracked file in the backup manifest, or remove
+/// it again when no schema file is given.
+pub fn set_schema(
+    backup_name: &str,
+    original_path: &str,
+    schema_file: Option<&str>,
+) -> io::Result<()> {
+    let (key, backup_file) = get_backup_entry(backup_name, original_path)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    let schema = match schema_file {
+        Some(schema_file) => {
+            let schema = Schema::parse(&fs::read_to_string(schema_file)?)?;
+            // Don't store a schema that the file already fails:
+            let problems = schema.validate(&DotEnv::parse(&fs::read_to_string(backup_file)?)?);
+            if !problems.is_empty() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "{original_path} does not match the schema:\n  {}",
+                        problems.join("\n  ")
+                    ),
+                ));
+            }
+            Some(schema)
+        }
+        None => None,
+    };
+    let key = paths.primary(&key).to_string();
+    paths.meta.entry(key).or_default().schema = schema;
+    set_backup_paths(backup_name, paths)
+}
+
+/// Validate every tracked dotenv file that has a schema, in the named
+/// backup or in all backups. Returns the number of files checked.
+pub fn check_backups(backup_name: Option<&str>) -> io::Result<usize> {
+    let backups: Vec<String> = match backup_name {
+        Some(backup_name) => vec![backup_name.to_string()],
+        None => load_config()?.backups.keys().cloned().collect(),
+    };
+    let mut checked = 0;
+    let mut failures = Vec::new();
+    for backup in backups {
+        let paths = match get_backup_paths(&backup) {
+            Err(e) if e.kind() == ErrorKind::NotFound && backup_name.is_none() => continue,
+            paths => paths?,
+        };
+        for (original_path, id) in &paths.files {
+            // Aliases share the contents of their primary entry:
+            if !is_dotenv_file_name(original_path) || paths.primary(original_path) != original_path
+            {
+                continue;
+            }
+            let Some(schema) = find_schema(&backup, original_path)? else {
+                continue;
+            };
+            debug!("checking {original_path} against its schema");
+            checked += 1;
+            let backup_file = get_backup_dir(&backup)?.join(id);
+            let problems = schema.validate(&DotEnv::parse(&fs::read_to_string(backup_file)?)?);
+            if !problems.is_empty() {
+                failures.push(format!("{original_path}:\n  {}", problems.join("\n  ")));
+            }
+        }
+    }
+    if failures.is_empty() {
+        Ok(checked)
+    } else {
+        Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Schema validation failed:\n{}", failures.join("\n")),
+        ))
+    }
+}
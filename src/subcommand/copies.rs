@@ -0,0 +1,147 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::audit::BackupAudits;
+use crate::config::load_config;
+use crate::diff::diff_lines;
+use crate::dotenv::is_dotenv_file_name;
+use crate::dry_run::is_dry_run;
+use crate::history::record_operation;
+use crate::paths::{
+    content_hash, get_backup_dir, get_backup_file, get_backup_paths, set_backup_paths, LinkMode,
+};
+use crate::placement::{place_undoable, record_placement, refresh_placements, write_backup_file};
+use crate::rotation::record_change;
+use crate::schema::validate_text;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// What `sync-copies` did (or could not do) for one tracked copy.
+#[derive(Debug, PartialEq)]
+enum CopySync {
+    InSync,
+    Pulled,
+    Pushed,
+    Missing,
+    Conflict(String), // a diff of the backup and the copy
+}
+
+/// Bring one tracked copy and its backup file back in sync, using the
+/// hash recorded when they were last in sync to tell which side changed.
+fn sync_copy(backup_name: &str, original_path: &str) -> io::Result<CopySync> {
+    let mut paths = get_backup_paths(backup_name)?;
+    let backup = get_backup_dir(backup_name)?.join(&paths.files[original_path]);
+    let copy = match fs::read(original_path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(CopySync::Missing),
+        copy => copy?,
+    };
+    let current = fs::read(&backup)?;
+    if copy == current {
+        record_placement(&mut paths, original_path, LinkMode::Copy, &backup)?;
+        set_backup_paths(backup_name, paths)?;
+        return Ok(CopySync::InSync);
+    }
+    let base = paths
+        .meta
+        .get(original_path)
+        .and_then(|m| m.copy_hash.as_deref());
+    let copy_changed = base != Some(content_hash(&copy).as_str());
+    let backup_changed = base != Some(content_hash(&current).as_str());
+    match (copy_changed, backup_changed) {
+        (true, false) => {
+            if is_dotenv_file_name(original_path) {
+                let text = String::from_utf8(copy.clone()).map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidData, "The copy is not valid UTF-8")
+                })?;
+                validate_text(backup_name, original_path, &text)?;
+            }
+            write_backup_file(backup_name, original_path, &copy)?;
+            record_change(backup_name, original_path, Some(&current), &copy)?;
+            // Other copies and hard links of the same file follow the backup:
+            refresh_placements(backup_name, original_path, Some(&current))?;
+            let mut paths = get_backup_paths(backup_name)?;
+            let backup = get_backup_file(backup_name, original_path)?;
+            record_placement(&mut paths, original_path, LinkMode::Copy, &backup)?;
+            set_backup_paths(backup_name, paths)?;
+            Ok(CopySync::Pulled)
+        }
+        (false, true) => {
+            place_undoable(
+                &mut paths,
+                LinkMode::Copy,
+                &backup,
+                Path::new(original_path),
+            )?;
+            set_backup_paths(backup_name, paths)?;
+            Ok(CopySync::Pushed)
+        }
+        _ => Ok(CopySync::Conflict(diff_lines(
+            ("backup", &String::from_utf8_lossy(&current)),
+            ("copy", &String::from_utf8_lossy(&copy)),
+        ))),
+    }
+}
+
+/// Sync the tracked copies of one backup, or of all of them. Changes to a
+/// copy are pulled into the backup, and changes to the backup are pushed
+/// out to the copy. Returns false if any copy could not be synced.
+pub fn sync_copies(backup_name: Option<&str>) -> io::Result<bool> {
+    let backup_names: Vec<String> = match backup_name {
+        Some(name) => vec![name.to_string()],
+        None => load_config()?.backups.keys().cloned().collect(),
+    };
+    let mut ok = true;
+    let mut audits = BackupAudits::default();
+    for backup_name in &backup_names {
+        let paths = match get_backup_paths(backup_name) {
+            // A backup without a paths.ron has no files yet:
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                // (the backups synced before this one are still audited)
+                audits.finish()?;
+                return Err(e);
+            }
+            Ok(paths) => paths,
+        };
+        let copies: Vec<&String> = paths
+            .files
+            .keys()
+            .filter(|path| paths.mode(path) == LinkMode::Copy)
+            .collect();
+        for path in copies {
+            let command = format!("sync-copies {path}");
+            match record_operation(backup_name, &command, || sync_copy(backup_name, path)) {
+                Ok(CopySync::InSync) => debug!("Copy is in sync: {path}"),
+                // (a dry run printed what it would do instead)
+                Ok(CopySync::Pulled | CopySync::Pushed) if is_dry_run() => (),
+                Ok(CopySync::Pulled) => {
+                    audits.touch(backup_name, "sync-copies", path, true);
+                    info!("Pulled changes from the copy into the backup: {path}")
+                }
+                Ok(CopySync::Pushed) => {
+                    audits.touch(backup_name, "sync-copies", path, true);
+                    info!("Pushed changes from the backup to the copy: {path}")
+                }
+                Ok(CopySync::Missing) => warn!(
+                    "Copy is missing: {path} (run `{} restore {backup_name}` to replace it)",
+                    env!("CARGO_BIN_NAME")
+                ),
+                Ok(CopySync::Conflict(diff)) => {
+                    ok = false;
+                    audits.touch(backup_name, "sync-copies", path, false);
+                    eprintln!(
+                        "Conflict: both the copy and the backup of {path} have changed:\n{diff}"
+                    );
+                }
+                Err(e) => {
+                    ok = false;
+                    audits.touch(backup_name, "sync-copies", path, false);
+                    eprintln!("Could not sync {path}: {e}");
+                }
+            }
+        }
+    }
+    audits.finish()?;
+    Ok(ok)
+}
@@ -0,0 +1,87 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::atomic::PrivateTempDir;
+use crate::dotenv::is_dotenv_file_name;
+use crate::paths::get_backup_file;
+use crate::placement::{refresh_placements, write_backup_file};
+use crate::rotation::record_change;
+use crate::schema::validate_text;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::Command;
+
+fn run_editor(path: &Path) -> io::Result<()> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    debug!("editor: {editor:?}");
+    // Run through the shell, so that EDITOR may contain arguments:
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg("sh")
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Editor exited unsuccessfully ({status}). The backup was not modified."
+        )));
+    }
+    Ok(())
+}
+
+pub fn edit_backup_file(backup_name: &str, original_path: &str) -> io::Result<()> {
+    let backup_file = get_backup_file(backup_name, original_path)?;
+    debug!("backup_file: {backup_file:?}");
+    let original_contents = fs::read(&backup_file)?;
+
+    // Edit a private copy, so the editor never touches the symlink or the backup:
+    let temp_dir = PrivateTempDir::new()?;
+    let file_name = Path::new(original_path)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or("secret");
+    let temp_file = temp_dir.create_file(file_name, &original_contents)?;
+    debug!("temp_file: {temp_file:?}");
+    run_editor(&temp_file)?;
+
+    // Validate the result:
+    let metadata = fs::symlink_metadata(&temp_file).map_err(|_| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            "The edited file was removed. The backup was not modified.",
+        )
+    })?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "The edited file is no longer a regular file. The backup was not modified.",
+        ));
+    }
+    let new_contents = fs::read(&temp_file)?;
+    if new_contents == original_contents {
+        return Err(io::Error::other(
+            "File unchanged. The backup was not modified.",
+        ));
+    }
+
+    if is_dotenv_file_name(original_path) {
+        let text = String::from_utf8(new_contents.clone()).map_err(|_| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "The edited file is not valid UTF-8. The backup was not modified.",
+            )
+        })?;
+        validate_text(backup_name, original_path, &text)?;
+    }
+
+    write_backup_file(backup_name, original_path, &new_contents)?;
+    record_change(
+        backup_name,
+        original_path,
+        Some(&original_contents),
+        &new_contents,
+    )?;
+    refresh_placements(backup_name, original_path, Some(&original_contents))
+}
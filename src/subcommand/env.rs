@@ -0,0 +1,97 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::subcommand::run::load_env_files;
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
+use clap_complete::Shell;
+use std::io;
+use std::str::FromStr;
+
+/// The shell to print statements for: one of the shells that
+/// `completions` supports, or plain POSIX sh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvShell {
+    Shell(Shell),
+    Posix,
+}
+
+impl EnvShell {
+    /// The names of the shells, for the command line.
+    pub fn possible_values() -> Vec<PossibleValue> {
+        Shell::value_variants()
+            .iter()
+            .filter_map(|shell| shell.to_possible_value())
+            .chain([PossibleValue::new("posix")])
+            .collect()
+    }
+
+    /// Guess the user's shell from $SHELL, falling back to POSIX sh.
+    pub fn detect() -> EnvShell {
+        Shell::from_env().map_or(EnvShell::Posix, EnvShell::Shell)
+    }
+}
+
+impl FromStr for EnvShell {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "posix" => Ok(EnvShell::Posix),
+            s => s.parse().map(EnvShell::Shell),
+        }
+    }
+}
+
+fn is_shell_variable_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn quote_posix(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// PowerShell and Elvish both double a quote inside single quotes.
+fn quote_doubled(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Format the statements that export (or unset) each variable, for eval
+/// by the given shell.
+pub fn env_statements(
+    backup_name: &str,
+    original_path: &str,
+    shell: EnvShell,
+    unset: bool,
+) -> io::Result<Vec<String>> {
+    let vars = load_env_files(backup_name, &[original_path.to_string()])?;
+    let mut statements = Vec::new();
+    for (key, value) in vars {
+        if !is_shell_variable_name(&key) {
+            warn!("Skipping key that is not a valid shell variable name: {key}");
+            continue;
+        }
+        statements.push(match (shell, unset) {
+            (EnvShell::Shell(Shell::Fish), false) => {
+                format!("set -gx {key} {}", quote_fish(&value))
+            }
+            (EnvShell::Shell(Shell::Fish), true) => format!("set -e {key}"),
+            (EnvShell::Shell(Shell::PowerShell), false) => {
+                format!("$env:{key} = {}", quote_doubled(&value))
+            }
+            (EnvShell::Shell(Shell::PowerShell), true) => format!("Remove-Item Env:{key}"),
+            (EnvShell::Shell(Shell::Elvish), false) => {
+                format!("set-env {key} {}", quote_doubled(&value))
+            }
+            (EnvShell::Shell(Shell::Elvish), true) => format!("unset-env {key}"),
+            (_, false) => format!("export {key}={}", quote_posix(&value)),
+            (_, true) => format!("unset {key}"),
+        });
+    }
+    Ok(statements)
+}
@@ -0,0 +1,93 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dotenv::DotEnv;
+use crate::dry_run::pretend;
+use crate::paths::get_backup_file;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The default example path is next to the original: `.env` -> `.env.example`
+fn example_path(original_path: &str, output: Option<&str>) -> PathBuf {
+    match output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let original = Path::new(original_path);
+            let name = original
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or(".env");
+            original.with_file_name(format!("{name}.example"))
+        }
+    }
+}
+
+fn load_backup_dotenv(backup_name: &str, original_path: &str) -> io::Result<DotEnv> {
+    let backup_file = get_backup_file(backup_name, original_path)?;
+    DotEnv::parse(&fs::read_to_string(backup_file)?)
+}
+
+fn keys(dotenv: &DotEnv) -> BTreeSet<String> {
+    dotenv.entries().iter().map(|e| e.key.clone()).collect()
+}
+
+/// Write a redacted copy of the dotenv file, keeping the keys and comments.
+pub fn write_example(
+    backup_name: &str,
+    original_path: &str,
+    output: Option<&str>,
+    hints: bool,
+) -> io::Result<PathBuf> {
+    let mut dotenv = load_backup_dotenv(backup_name, original_path)?;
+    dotenv.redact(hints);
+    let example = example_path(original_path, output);
+    if fs::symlink_metadata(&example).is_ok_and(|m| m.is_symlink()) {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Refusing to overwrite symlink: {}", example.display()),
+        ));
+    }
+    if !pretend(|| format!("write {}", example.display())) {
+        fs::write(&example, dotenv.to_string())?;
+    }
+    Ok(example)
+}
+
+/// Check that the example file has exactly the same keys as the dotenv file.
+pub fn check_example(
+    backup_name: &str,
+    original_path: &str,
+    output: Option<&str>,
+) -> io::Result<()> {
+    let dotenv = load_backup_dotenv(backup_name, original_path)?;
+    let example = example_path(original_path, output);
+    let example_dotenv = DotEnv::parse(&fs::read_to_string(&example).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read {}: {e}", example.display()),
+        )
+    })?)?;
+    let (expected, actual) = (keys(&dotenv), keys(&example_dotenv));
+    let missing: Vec<&String> = expected.difference(&actual).collect();
+    let extra: Vec<&String> = actual.difference(&expected).collect();
+    if missing.is_empty() && extra.is_empty() {
+        return Ok(());
+    }
+    let mut problems = Vec::new();
+    if !missing.is_empty() {
+        problems.push(format!("missing keys: {missing:?}"));
+    }
+    if !extra.is_empty() {
+        problems.push(format!("extra keys: {extra:?}"));
+    }
+    Err(io::Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "{} is out of date ({})",
+            example.display(),
+            problems.join(", ")
+        ),
+    ))
+}
@@ -0,0 +1,156 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dry_run::pretend;
+use crate::history;
+use crate::paths::{get_backup_dir, get_backup_file};
+use crate::subcommand::add::add_to_backup;
+use crate::subcommand::key::{get_key, set_key};
+use crate::transaction::Undo;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use std::fs::OpenOptions;
+use std::io::{self, ErrorKind};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+const ALNUM: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const HEX: &[u8] = b"0123456789abcdef";
+
+/// 256 short words, so each word adds 8 bits of entropy.
+const WORDS: [&str; 256] = [
+    "acid", "acorn", "actor", "adobe", "agent", "alarm", "album", "alley", "alpha", "amber",
+    "angle", "ankle", "apple", "apron", "arena", "arrow", "atlas", "attic", "audio", "award",
+    "bacon", "badge", "bagel", "baker", "banjo", "barn", "basil", "beach", "beard", "bench",
+    "berry", "bike", "birch", "bison", "blade", "blimp", "bloom", "board", "bonus", "boots",
+    "brain", "brick", "broom", "brush", "cabin", "cable", "cacao", "camel", "candy", "canoe",
+    "cargo", "cedar", "chalk", "charm", "chess", "chili", "cider", "cigar", "civic", "clamp",
+    "cliff", "clock", "cloud", "coast", "cobra", "comet", "coral", "couch", "crane", "creek",
+    "crown", "cube", "curry", "daisy", "dance", "delta", "denim", "depot", "diary", "disco",
+    "dock", "dodge", "dough", "draft", "drum", "dune", "eagle", "easel", "echo", "elbow", "elder",
+    "ember", "emoji", "epoch", "fable", "fairy", "fence", "ferry", "fiber", "field", "flame",
+    "flute", "focus", "forge", "fox", "frost", "fudge", "gecko", "ghost", "giant", "glove",
+    "gnome", "goose", "grape", "gravy", "guava", "guide", "habit", "harp", "hazel", "heron",
+    "hinge", "honey", "hotel", "husky", "igloo", "inlet", "iris", "ivory", "jazz", "jelly",
+    "jewel", "joker", "judge", "juice", "kayak", "kebab", "kettle", "kiosk", "koala", "label",
+    "ladle", "lemon", "lilac", "linen", "llama", "lobby", "lodge", "lotus", "lunar", "mango",
+    "maple", "march", "medal", "melon", "metro", "mint", "mocha", "moose", "motor", "mural",
+    "nacho", "navy", "nectar", "noble", "north", "novel", "nylon", "oasis", "ocean", "olive",
+    "omega", "onion", "opera", "orbit", "otter", "oxide", "paddle", "panda", "pasta", "peach",
+    "pearl", "pecan", "pepper", "piano", "pilot", "pixel", "pizza", "plaza", "polka", "poppy",
+    "prism", "pulse", "quail", "quartz", "quest", "quilt", "radar", "radio", "raven", "relay",
+    "rhino", "ridge", "river", "robin", "rodeo", "rover", "ruby", "saddle", "salsa", "sauna",
+    "scarf", "scout", "shark", "shelf", "sierra", "silk", "skate", "slate", "sloth", "snack",
+    "solar", "sonic", "spice", "spoon", "squid", "stamp", "stone", "storm", "sugar", "swamp",
+    "tango", "thorn", "tiger", "toast", "topaz", "torch", "tulip", "tuner", "ultra", "umbra",
+    "unity", "urban", "valve", "vapor", "velvet", "viola", "vivid", "wafer", "waltz", "whale",
+    "wheat", "willow", "yacht", "zebra", "zesty",
+];
+
+/// The shortest length that gives a value of the charset at least 64 bits
+/// of entropy.
+fn min_length(charset: &str) -> usize {
+    match charset {
+        "hex" => 16,
+        "words" => 8,
+        _ => 11, // alnum and base64, with about 6 bits per character
+    }
+}
+
+/// Generate a random value of the given length from the charset. For the
+/// `words` charset, the length is the number of words.
+pub fn generate_value(length: usize, charset: &str) -> io::Result<String> {
+    let min = min_length(charset);
+    if length < min {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "A length of {length} is too weak for --charset {charset} (the minimum is {min})"
+            ),
+        ));
+    }
+    let mut rng = OsRng;
+    let pick = |alphabet: &[u8], rng: &mut OsRng| -> String {
+        (0..length)
+            .map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char)
+            .collect()
+    };
+    match charset {
+        "alnum" => Ok(pick(ALNUM, &mut rng)),
+        "hex" => Ok(pick(HEX, &mut rng)),
+        "base64" => {
+            let mut bytes = vec![0u8; length * 3 / 4 + 3];
+            rng.fill_bytes(&mut bytes);
+            let mut value = URL_SAFE_NO_PAD.encode(bytes);
+            value.truncate(length);
+            Ok(value)
+        }
+        "words" => Ok((0..length)
+            .map(|_| WORDS[rng.gen_range(0..WORDS.len())])
+            .collect::<Vec<_>>()
+            .join("-")),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unsupported charset: {charset}"),
+        )),
+    }
+}
+
+/// Make sure the file is tracked in the backup, creating an empty
+/// (private) file first if it does not exist.
+fn ensure_tracked(backup_name: &str, original_path: &str) -> io::Result<()> {
+    get_backup_dir(backup_name)?;
+    match get_backup_file(backup_name, original_path) {
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => return Err(e),
+    }
+    if !Path::new(original_path).exists() {
+        if pretend(|| format!("create {original_path}, and add it to backup '{backup_name}'")) {
+            return Ok(());
+        }
+        debug!("creating new file: {original_path}");
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(original_path)?;
+        history::record(Undo::Remove(PathBuf::from(original_path)));
+    }
+    add_to_backup(backup_name, original_path)?;
+    info!(
+        "File '{}' added to backup '{}'.",
+        original_path, backup_name
+    );
+    Ok(())
+}
+
+/// Generate a new random value for the key. Returns false if the key
+/// already had a value and `if_missing` was set, so nothing was changed.
+pub fn generate_secret(
+    backup_name: &str,
+    original_path: &str,
+    key: &str,
+    length: usize,
+    charset: &str,
+    if_missing: bool,
+) -> io::Result<bool> {
+    let value = generate_value(length, charset)?;
+    ensure_tracked(backup_name, original_path)?;
+    if if_missing {
+        match get_key(backup_name, original_path, key, None) {
+            Ok(_) => return Ok(false),
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+    }
+    // (in a dry run, a new file was not really added)
+    let added = get_backup_file(backup_name, original_path).is_ok_and(|f| f.exists());
+    if !added && pretend(|| format!("set {key} in {original_path}")) {
+        return Ok(true);
+    }
+    set_key(backup_name, original_path, key, &value, None)?;
+    Ok(true)
+}
@@ -0,0 +1,60 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::history::{is_undone, read_history};
+use crate::rotation::now;
+use prettytable::{format::consts as fmt, Cell, Row, Table};
+use serde_json::json;
+use std::io;
+
+/// How long ago a time was, roughly.
+fn ago(time: u64) -> String {
+    match now().saturating_sub(time) {
+        s if s < 60 => format!("{s}s ago"),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h ago", s / (60 * 60)),
+        s => format!("{}d ago", s / (24 * 60 * 60)),
+    }
+}
+
+/// Print the operations in the history of the backup, oldest first.
+pub fn print_history(backup_name: &str, output_as_json: bool) -> io::Result<()> {
+    let history = read_history(backup_name)?;
+    if output_as_json {
+        let json_operations: Vec<serde_json::Value> = history
+            .iter()
+            .map(|op| {
+                json!({
+                    "seq": op.seq,
+                    "time": op.time,
+                    "command": op.command,
+                    "undoes": op.undoes,
+                    "undone": is_undone(&history, op.seq),
+                })
+            })
+            .collect();
+        let json_output = json!({
+            "backup_name": backup_name,
+            "operations": json_operations,
+        });
+        println!("{}", serde_json::to_string_pretty(&json_output).unwrap());
+    } else {
+        let mut table = Table::new();
+        table.set_format(*fmt::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(Row::new(
+            ["#", "When", "Command", ""]
+                .iter()
+                .map(|x| Cell::new(x))
+                .collect(),
+        ));
+        for op in &history {
+            let undone = match is_undone(&history, op.seq) {
+                true => "(undone)",
+                false => "",
+            };
+            table.add_row(row![op.seq, ago(op.time), op.command, undone]);
+        }
+        table.printstd();
+    }
+    Ok(())
+}
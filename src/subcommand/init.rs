@@ -1,6 +1,7 @@
 use crate::config::{load_config, save_config};
+use crate::dry_run;
 use crate::get_options;
-use crate::paths::get_backup_paths;
+use crate::paths::{absolute_path, get_backup_paths, set_backup_paths, Layout, Paths};
 #[allow(unused_imports)]
 use crate::prelude::*;
 
@@ -8,7 +9,7 @@ use std::fs;
 use std::io::{self};
 use std::path::{Path, PathBuf};
 
-pub fn init_backup(backup_name: &str, path: Option<&str>) -> io::Result<()> {
+pub fn init_backup(backup_name: &str, path: Option<&str>, layout: Layout) -> io::Result<()> {
     debug!("init backup: {:?}", &backup_name);
     // Resolve the provided path or default to the current directory
     let backup_path = Path::new(path.unwrap_or("."));
@@ -33,14 +34,31 @@ pub fn init_backup(backup_name: &str, path: Option<&str>) -> io::Result<()> {
 
     debug!("creating directory: {:?}", &backup_path);
     // Create the backup directory
-    fs::create_dir(backup_path)?;
+    dry_run::create_dir(backup_path)?;
 
     // Convert to absolute path
-    let absolute_backup_path = fs::canonicalize(backup_path)?;
+    let absolute_backup_path = match dry_run::is_dry_run() {
+        // (the directory was not created)
+        true => absolute_path(&backup_path.to_string_lossy()),
+        false => fs::canonicalize(backup_path)?,
+    };
 
     // Update the config with the absolute path
     update_config(backup_name, &absolute_backup_path)?;
 
+    // The default policy doesn't need a paths.ron until a file is added:
+    let relative_links = get_options().relative;
+    if layout != Layout::default() || relative_links {
+        set_backup_paths(
+            backup_name,
+            Paths {
+                layout,
+                relative_links,
+                ..Default::default()
+            },
+        )?;
+    }
+
     Ok(())
 }
 
@@ -51,8 +69,8 @@ pub fn update_config(backup_name: &str, path: &Path) -> io::Result<()> {
         .expect("Could not discover config file parent directory");
 
     // Ensure the parent directory exists
-    if !config_dir.exists() {
-        fs::create_dir_all(&config_dir)?;
+    if !config_dir.as_os_str().is_empty() && !config_dir.exists() {
+        dry_run::create_dir_all(config_dir)?;
     }
 
     // Load or initialize the configuration
@@ -82,10 +100,12 @@ pub fn deinit_backup(backup_name: &str) -> io::Result<()> {
         // Save the updated config back to the file using the common save_config method
         save_config(&config)?;
 
-        println!(
-            "Backup '{}' has been removed from the configuration.",
-            backup_name
-        );
+        if !dry_run::is_dry_run() {
+            println!(
+                "Backup '{}' has been removed from the configuration.",
+                backup_name
+            );
+        }
         Ok(())
     };
 
@@ -0,0 +1,78 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::document::{self, Format};
+use crate::paths::get_backup_file;
+use crate::placement::{refresh_placements, write_backup_file};
+use crate::rotation::record_change;
+use crate::schema::validate_text;
+use std::fs;
+use std::io::{self, ErrorKind};
+
+fn with_path_context(e: io::Error, original_path: &str) -> io::Error {
+    match e.kind() {
+        ErrorKind::NotFound => io::Error::new(e.kind(), format!("{e} in {original_path}")),
+        _ => e,
+    }
+}
+
+pub fn get_key(
+    backup_name: &str,
+    original_path: &str,
+    key: &str,
+    format: Option<Format>,
+) -> io::Result<String> {
+    let backup_file = get_backup_file(backup_name, original_path)?;
+    let format = format.unwrap_or(Format::detect(original_path));
+    let text = fs::read_to_string(backup_file)?;
+    document::get(format, &text, key).map_err(|e| with_path_context(e, original_path))
+}
+
+pub fn set_key(
+    backup_name: &str,
+    original_path: &str,
+    key: &str,
+    value: &str,
+    format: Option<Format>,
+) -> io::Result<()> {
+    let backup_file = get_backup_file(backup_name, original_path)?;
+    let format = format.unwrap_or(Format::detect(original_path));
+    let old_text = fs::read_to_string(&backup_file)?;
+    let text = document::set(format, &old_text, key, value)
+        .map_err(|e| with_path_context(e, original_path))?;
+    if format == Format::Dotenv {
+        validate_text(backup_name, original_path, &text)?;
+    }
+    write_backup_file(backup_name, original_path, text.as_bytes())?;
+    record_change(
+        backup_name,
+        original_path,
+        Some(old_text.as_bytes()),
+        text.as_bytes(),
+    )?;
+    refresh_placements(backup_name, original_path, Some(old_text.as_bytes()))
+}
+
+pub fn unset_key(
+    backup_name: &str,
+    original_path: &str,
+    key: &str,
+    format: Option<Format>,
+) -> io::Result<()> {
+    let backup_file = get_backup_file(backup_name, original_path)?;
+    let format = format.unwrap_or(Format::detect(original_path));
+    let old_text = fs::read_to_string(&backup_file)?;
+    let text =
+        document::unset(format, &old_text, key).map_err(|e| with_path_context(e, original_path))?;
+    if format == Format::Dotenv {
+        validate_text(backup_name, original_path, &text)?;
+    }
+    write_backup_file(backup_name, original_path, text.as_bytes())?;
+    record_change(
+        backup_name,
+        original_path,
+        Some(old_text.as_bytes()),
+        text.as_bytes(),
+    )?;
+    refresh_placements(backup_name, original_path, Some(old_text.as_bytes()))
+}
@@ -0,0 +1,78 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dry_run;
+use crate::paths::{
+    get_backup_entry, get_backup_paths, resolve_original_path, set_backup_paths, LinkMode,
+};
+use crate::placement::{place_undoable, Placement};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Create another symlink to an already backed up file, and record it
+/// as an alias of the existing path.
+pub fn link_file(backup_name: &str, existing_path: &str, new_path: &str) -> io::Result<()> {
+    let (key, backup_file) = get_backup_entry(backup_name, existing_path)?;
+    let new = resolve_original_path(new_path);
+    if fs::symlink_metadata(&new).is_ok() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Path already exists: {new_path}"),
+        ));
+    }
+    let mut paths = get_backup_paths(backup_name)?;
+    dry_run::symlink(&paths, &backup_file, &new)?;
+    debug!("linked {new:?} -> {backup_file:?}");
+    paths.insert_alias(&key, new.to_str().expect("failed to_str on path"));
+    set_backup_paths(backup_name, paths)
+}
+
+/// Remove one of the paths linked to a backed up file, leaving the
+/// backup and its other paths alone.
+pub fn unlink_file(backup_name: &str, path: &str) -> io::Result<()> {
+    let (key, backup_file) = get_backup_entry(backup_name, path)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    // (in a content-addressed backup, unrelated files may share the blob)
+    if paths.linked_entries(&key).len() < 2 {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{path} is the only path linked to this file. Use `rm` to remove it from the backup instead."),
+        ));
+    }
+    // Only remove the link (or copy) if it is still current:
+    let original = Path::new(&key);
+    if Placement::of(paths.mode(&key), original, &backup_file).is_current() {
+        dry_run::remove_file(original)?;
+    }
+    paths.remove(&key);
+    set_backup_paths(backup_name, paths)
+}
+
+/// Change how a backed up file is placed at its path, and place it again
+/// in the new mode. A file with changes not in the backup is left alone.
+pub fn set_link_mode(backup_name: &str, path: &str, mode: LinkMode) -> io::Result<()> {
+    let (key, backup_file) = get_backup_entry(backup_name, path)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    let original = Path::new(&key);
+    match Placement::of(paths.mode(&key), original, &backup_file) {
+        Placement::DivergedCopy | Placement::UntrackedCopy | Placement::StaleHardlink => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{path} differs from the backup. Restore it with `{} restore --overwrite` first.",
+                    env!("CARGO_BIN_NAME")
+                ),
+            ));
+        }
+        Placement::Conflict => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("A conflicting non-backup file exists at {path}"),
+            ));
+        }
+        Placement::Missing => paths.set_mode(&key, mode),
+        _ => place_undoable(&mut paths, mode, &backup_file, original)?,
+    }
+    set_backup_paths(backup_name, paths)
+}
@@ -1,14 +1,14 @@
-use crate::paths::{expand_tilde_path, shorten_path};
+use crate::paths::{expand_tilde_path, shorten_path, Paths};
 #[allow(unused_imports)]
 use crate::prelude::*;
 use indexmap::IndexMap;
-use serde::Deserialize;
 use serde_json::json;
 use std::fs::File;
 use std::io::{self, ErrorKind};
 use std::path::Path;
 
 use crate::config::load_config;
+use crate::selector::Selector;
 use prettytable::{format::consts as fmt, Cell, Row, Table};
 
 fn get_backups() -> io::Result<Vec<String>> {
@@ -67,11 +67,6 @@ pub fn list_backups(output_as_json: bool) -> io::Result<()> {
     }
 }
 
-#[derive(Deserialize)]
-struct Paths {
-    files: IndexMap<String, String>, // original_path -> backup_path
-}
-
 fn get_backup_path(backup_name: &str) -> io::Result<String> {
     let config = load_config()?;
     match config
@@ -94,7 +89,12 @@ fn get_backup_path(backup_name: &str) -> io::Result<String> {
     }
 }
 
-fn get_backup_files(backup_name: &str) -> io::Result<Vec<String>> {
+/// The selected files in the backup, with the original path that each alias
+/// links to.
+fn get_backup_files(
+    backup_name: &str,
+    selector: &Selector,
+) -> io::Result<Vec<(String, Option<String>)>> {
     let backup_dir = get_backup_path(backup_name)?;
     let paths_file = Path::new(&backup_dir).join("paths.ron");
 
@@ -116,10 +116,22 @@ fn get_backup_files(backup_name: &str) -> io::Result<Vec<String>> {
         }
     };
 
-    Ok(paths.files.keys().cloned().collect())
+    Ok(paths
+        .files
+        .keys()
+        .filter(|f| selector.matches(&paths, f))
+        .map(|f| {
+            let alias_of = paths.meta.get(f).and_then(|m| m.alias_of.clone());
+            (f.clone(), alias_of)
+        })
+        .collect())
 }
 
-pub fn list_backup_files(backup_name: &str, output_as_json: bool) -> io::Result<()> {
+pub fn list_backup_files(
+    backup_name: &str,
+    selector: &Selector,
+    output_as_json: bool,
+) -> io::Result<()> {
     let backup_dir = &get_backup_path(backup_name)?;
     let backup_dir = Path::new(backup_dir);
     if !backup_dir.exists() {
@@ -128,37 +140,57 @@ pub fn list_backup_files(backup_name: &str, output_as_json: bool) -> io::Result<
             format!("Backup directory does not exist: {backup_dir:?}"),
         ));
     }
-    match get_backup_files(backup_name) {
+    match get_backup_files(backup_name, selector) {
         Ok(files) => {
             if !files.is_empty() {
                 if output_as_json {
-                    let expanded_files: Vec<String> = files
+                    let expand = |file: &str| {
+                        let exp = expand_tilde_path(file).expect("failed to expand path");
+                        exp.to_str().expect("failed to stringify path").to_string()
+                    };
+                    let expanded_files: Vec<String> =
+                        files.iter().map(|(file, _)| expand(file)).collect();
+                    debug!("expanded_files: {:?}", expanded_files);
+                    let aliases: IndexMap<String, String> = files
                         .iter()
-                        .map(|file| {
-                            let exp = expand_tilde_path(file).expect("failed to expand path");
-                            exp.to_str().expect("failed to stringify path").to_string()
+                        .filter_map(|(file, alias_of)| {
+                            alias_of.as_ref().map(|a| (expand(file), expand(a)))
                         })
                         .collect();
-                    debug!("expanded_files: {:?}", expanded_files);
-                    let json_output = json!({
+                    let mut json_output = json!({
                         "backup_name": backup_name,
                         "files": expanded_files,
                     });
+                    if !aliases.is_empty() {
+                        json_output["aliases"] = json!(aliases);
+                    }
 
                     println!("{}", serde_json::to_string_pretty(&json_output).unwrap());
                 } else {
                     let mut table = get_table(vec![&format!(
                         "Local files contained in backup ({backup_name}):"
                     )]);
-                    for file in files {
+                    for (file, alias_of) in files {
                         let exp = expand_tilde_path(&file).expect("failed to expand path");
                         let f = exp.as_path().to_str().expect("failed to stringify path");
-                        table.add_row(row![shorten_path(f)]);
+                        match alias_of {
+                            Some(a) => table.add_row(row![format!(
+                                "{} (alias of {})",
+                                shorten_path(f),
+                                shorten_path(&a)
+                            )]),
+                            None => table.add_row(row![shorten_path(f)]),
+                        };
                     }
                     println!();
                     table.printstd();
                 }
                 Ok(())
+            } else if !selector.is_all() {
+                Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    "No files in the backup match",
+                ))
             } else {
                 Err(io::Error::new(
                     ErrorKind::InvalidData,
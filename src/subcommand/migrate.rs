@@ -0,0 +1,92 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dry_run::{is_dry_run, pretend};
+use crate::history;
+use crate::paths::{get_backup_dir, get_backup_paths, set_backup_paths, Layout, Paths};
+use crate::placement::{place, Placement};
+use crate::transaction::Transaction;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Convert a backup to the content-addressed layout. Every file is moved
+/// into a blob shared with any other file with the same contents, and the
+/// links and copies are pointed at the new blobs, all or nothing. Returns
+/// the number of files and the number of blobs they are stored in.
+pub fn migrate_backup(backup_name: &str) -> io::Result<(usize, usize)> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    let mut paths = match get_backup_paths(backup_name) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Default::default(),
+        paths => paths?,
+    };
+    if paths.layout == Layout::Content {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Backup '{backup_name}' is already content-addressed"),
+        ));
+    }
+    let mut tx = Transaction::new();
+    let result = convert(&mut paths, &backup_dir, &mut tx).and_then(|counts| {
+        set_backup_paths(backup_name, paths)?;
+        Ok(counts)
+    });
+    match result {
+        Ok(counts) => {
+            tx.commit()?.into_iter().for_each(history::record);
+            Ok(counts)
+        }
+        Err(e) => {
+            tx.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+/// Move every file into its blob, within the transaction. The old files
+/// are set aside, to be deleted only once the new manifest is written.
+fn convert(
+    paths: &mut Paths,
+    backup_dir: &Path,
+    tx: &mut Transaction,
+) -> io::Result<(usize, usize)> {
+    let old_files = std::mem::take(&mut paths.files);
+    paths.layout = Layout::Content;
+    paths.blobs.clear();
+    for (original_path, old_id) in &old_files {
+        let old_file = backup_dir.join(old_id);
+        let contents = fs::read(&old_file)?;
+        let (id, exists) = paths.find_blob(backup_dir, &contents);
+        let blob = backup_dir.join(&id);
+        // (in a dry run, the blob of an earlier file was not really created)
+        if !exists && !paths.blobs.contains_key(&id) {
+            let copying = || format!("copy {} to {}", old_file.display(), blob.display());
+            if !pretend(copying) {
+                tx.create(&blob);
+                // Copying keeps the permissions of the original:
+                fs::copy(&old_file, &blob)?;
+            }
+        }
+        paths.insert(original_path, &id);
+        // Only replace links and copies that are still current:
+        let original = Path::new(original_path);
+        let mode = paths.mode(original_path);
+        if Placement::of(mode, original, &old_file).is_current() {
+            debug!("relinking {original_path} to {id}");
+            if !is_dry_run() {
+                tx.set_aside(original)?;
+                tx.create(original);
+            }
+            place(paths, mode, &blob, original)?;
+        }
+    }
+    for old_id in old_files.values() {
+        let old_file = backup_dir.join(old_id);
+        if !paths.blobs.contains_key(old_id)
+            && !pretend(|| format!("remove {}", old_file.display()))
+        {
+            tx.set_aside(&old_file)?;
+        }
+    }
+    Ok((paths.files.len(), paths.blobs.len()))
+}
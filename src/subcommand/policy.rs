@@ -0,0 +1,30 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::paths::{get_backup_dir, get_backup_paths, set_backup_paths};
+use std::io::{self, ErrorKind};
+
+/// Changes to the policy of a backup. `None` leaves a setting unchanged.
+#[derive(Debug, Default)]
+pub struct PolicyUpdate {
+    pub max_age_days: Option<Option<u64>>,
+    pub relative_links: Option<bool>,
+}
+
+/// Update the policy of a backup, which is stored in its paths.ron.
+pub fn set_policy(backup_name: &str, update: PolicyUpdate) -> io::Result<()> {
+    let mut paths = match get_backup_paths(backup_name) {
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            get_backup_dir(backup_name)?;
+            Default::default()
+        }
+        paths => paths?,
+    };
+    if let Some(max_age_days) = update.max_age_days {
+        paths.max_age_days = max_age_days;
+    }
+    if let Some(relative_links) = update.relative_links {
+        paths.relative_links = relative_links;
+    }
+    set_backup_paths(backup_name, paths)
+}
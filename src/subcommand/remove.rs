@@ -1,41 +1,92 @@
 #[allow(unused_imports)]
 use crate::prelude::*;
 
-use crate::paths::{absolute_path, get_backup_paths, reverse_files_map, set_backup_paths};
+use crate::paths::{
+    absolute_path, get_backup_paths, resolve_original_path, reverse_files_map, set_backup_paths,
+    LinkMode, Paths,
+};
+use crate::placement::Placement;
 #[allow(unused_imports)]
 use crate::prelude::*;
+use crate::selector::Selector;
 
 use crate::config::load_config;
-use std::fs::{self, canonicalize};
+use crate::dry_run;
+use std::fs::canonicalize;
 use std::io::{self, ErrorKind};
 use std::path::Path;
 
-fn remove_backup_entry(backup_name: &str, original_path: &str) -> io::Result<()> {
+/// Remove the file from the paths map. Returns the id of the backup
+/// file, if no other entry shares it.
+fn remove_backup_entry(backup_name: &str, original_path: &str) -> io::Result<Option<String>> {
     let mut paths = get_backup_paths(backup_name).expect("Could not get backup paths");
-    // Remove the file from the paths map
     debug!("paths: {paths:?}");
     debug!("original_path: {original_path:?}");
-    paths.files.shift_remove(original_path);
-    set_backup_paths(backup_name, paths)
+    if !paths.files.contains_key(original_path) {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("File not found in backup: {original_path:?}"),
+        ));
+    }
+    let unused = paths.remove(original_path);
+    set_backup_paths(backup_name, paths)?;
+    Ok(unused)
 }
 
 fn destroy_backup_file(backup_name: &str, original_path: &str) -> io::Result<()> {
     debug!("original_path: {original_path:?}");
-    let paths = get_backup_paths(backup_name)?;
-    debug!("paths: {paths:?}");
-    let id = paths
-        .files
-        .get(original_path)
-        .expect("failed to get backup file entry");
     let config = load_config()?;
     let backup_dir = config
         .backups
         .get(backup_name)
         .ok_or(io::Error::new(ErrorKind::NotFound, "Backup not found"))?;
-    let backup_file = Path::new(backup_dir).join(Path::new(id));
-    debug!("backup_file: {backup_file:?}");
-    remove_backup_entry(backup_name, original_path)?;
-    fs::remove_file(backup_file)?;
+    match remove_backup_entry(backup_name, original_path)? {
+        Some(id) => {
+            let backup_file = Path::new(backup_dir).join(Path::new(&id));
+            debug!("backup_file: {backup_file:?}");
+            dry_run::remove_file(&backup_file)
+        }
+        None => {
+            debug!("backup file is still shared by other entries");
+            Ok(())
+        }
+    }
+}
+
+/// Remove a file placed as a hard link or a tracked copy. Without
+/// `delete`, a hard link is replaced by a copy, so that it outlives the
+/// backup file, and a copy is kept as it is.
+fn remove_placed_file(
+    backup_name: &str,
+    backup_dir: &Path,
+    paths: &Paths,
+    key: &str,
+    delete: bool,
+) -> io::Result<()> {
+    let original = Path::new(key);
+    let backup = backup_dir.join(&paths.files[key]);
+    let status = Placement::of(paths.mode(key), original, &backup);
+    match status {
+        Placement::Conflict => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("A conflicting non-backup file exists in the original path: '{key}'. To remove this entry from the backup without restoring it, add the --delete argument.")));
+        }
+        Placement::Missing if !delete => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "The existing path does not exist. To remove this entry from the backup, without restoring it, add the --delete argument.".to_string()));
+        }
+        Placement::Missing => (),
+        _ if delete => dry_run::remove_file(original)?,
+        Placement::Hardlink => {
+            dry_run::remove_file(original)?;
+            dry_run::copy(&backup, original)?;
+        }
+        _ => (),
+    }
+    destroy_backup_file(backup_name, key)?;
+    match delete {
+        _ if dry_run::is_dry_run() => (),
+        true => info!("File permanently deleted: {key:?}"),
+        false => info!("File restored and removed from backup: {key:?}"),
+    }
     Ok(())
 }
 
@@ -59,6 +110,13 @@ pub fn remove_from_backup(backup_name: &str, original_path: &str, delete: bool)
 
     let paths = get_backup_paths(backup_name).expect("failed to get backup paths");
 
+    // Hard links and copies are not symlinks into the backup:
+    let key = resolve_original_path(original_path);
+    let key = key.to_str().expect("failed to_str()");
+    if paths.files.contains_key(key) && paths.mode(key) != LinkMode::Symlink {
+        return remove_placed_file(backup_name, Path::new(backup_dir), &paths, key, delete);
+    }
+
     let canonical_path;
     match canonicalize(original_path) {
         Err(_) => {
@@ -92,7 +150,14 @@ pub fn remove_from_backup(backup_name: &str, original_path: &str, delete: bool)
             .to_str()
             .expect("failed to_str()");
         debug!("id: {id}");
-        match files.get(id) {
+        // Prefer the entry for this very path, since blobs may be shared:
+        let key = resolve_original_path(original_path);
+        let key = key.to_str().expect("failed to_str()");
+        let entry = match paths.files.get(key) {
+            Some(v) if v == id => Some(key),
+            _ => files.get(id).map(|f| f.as_str()),
+        };
+        match entry {
             None => Err(io::Error::new(
                 ErrorKind::InvalidData,
                 format!("File not found in backup: {original:?}"),
@@ -106,25 +171,29 @@ pub fn remove_from_backup(backup_name: &str, original_path: &str, delete: bool)
                 if delete {
                     // Remove the symlink at the original path
                     if original.exists() && original.is_symlink() {
-                        fs::remove_file(original)?;
+                        dry_run::remove_file(original)?;
                     }
                     debug!("removed symlink");
                     destroy_backup_file(backup_name, abs_path)
                         .expect("failed to remove backup file");
-                    info!("File permanently deleted: {original_path:?}");
+                    if !dry_run::is_dry_run() {
+                        info!("File permanently deleted: {original_path:?}");
+                    }
                 } else {
                     // Restore the original file by copying it from the backup
                     if original.exists() && original.is_symlink() {
-                        fs::remove_file(original)?; // Remove the symlink
+                        dry_run::remove_file(original)?; // Remove the symlink
                         debug!("removed_symlink");
                     }
                     debug!("backup: {backup:?}");
                     debug!("original: {original:?}");
-                    fs::copy(backup, original)?; // Restore the original file
+                    dry_run::copy(&backup, original)?; // Restore the original file
                     debug!("copied");
                     destroy_backup_file(backup_name, abs_path)
                         .expect("failed to remove backup file");
-                    info!("File restored and removed from backup: {original_path:?}");
+                    if !dry_run::is_dry_run() {
+                        info!("File restored and removed from backup: {original_path:?}");
+                    }
                 }
 
                 Ok(())
@@ -143,3 +212,20 @@ pub fn remove_from_backup(backup_name: &str, original_path: &str, delete: bool)
         return Err(io::Error::new(io::ErrorKind::InvalidData, "The existing path does not exist. To remove this entry from the backup, without restoring it, add the --delete argument.".to_string()));
     }
 }
+
+/// The files to remove: exact paths as they were given, or else the keys
+/// of the entries that the selector matches.
+pub fn select_files(backup_name: &str, selector: &Selector) -> io::Result<Vec<String>> {
+    if let Some(paths) = selector.exact_paths() {
+        return Ok(paths.to_vec());
+    }
+    let paths = get_backup_paths(backup_name)?;
+    let files: Vec<String> = selector.select(&paths).into_iter().cloned().collect();
+    if files.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            "No files in the backup match",
+        ));
+    }
+    Ok(files)
+}
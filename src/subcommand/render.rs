@@ -0,0 +1,165 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::atomic::atomic_write;
+use crate::audit::BackupAudits;
+use crate::config::load_config;
+use crate::document::{self, Format};
+use crate::dry_run::pretend;
+use crate::history::{self, record_operation};
+use crate::paths::{absolute_path, get_backup_file, resolve_original_path};
+use crate::placement::{refresh_placements, write_backup_file};
+use crate::rotation::record_change;
+use crate::subcommand::add::add_to_backup;
+use crate::transaction::Undo;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Look up the value of a single reference: `hc:BACKUP_NAME:PATH#KEY`.
+/// Without a `#KEY`, the entire contents of the file are used.
+fn resolve_reference(reference: &str) -> io::Result<String> {
+    let invalid = || {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid reference: {reference:?} (expected hc:BACKUP_NAME:PATH#KEY)"),
+        )
+    };
+    let rest = reference.strip_prefix("hc:").ok_or_else(invalid)?;
+    let (backup_name, path) = rest.split_once(':').ok_or_else(invalid)?;
+    let (path, key) = match path.rsplit_once('#') {
+        Some((path, key)) => (path, Some(key)),
+        None => (path, None),
+    };
+    if backup_name.is_empty() || path.is_empty() || key == Some("") {
+        return Err(invalid());
+    }
+    let text = fs::read_to_string(get_backup_file(backup_name, path)?)?;
+    match key {
+        Some(key) => document::get(Format::detect(path), &text, key)
+            .map_err(|e| io::Error::new(e.kind(), format!("{e} in {path}"))),
+        None => Ok(text),
+    }
+}
+
+/// Replace every `{{ hc:BACKUP_NAME:PATH#KEY }}` placeholder in the text.
+/// Any other `{{ ... }}` is left alone, so other template languages can
+/// be mixed in. Every unresolved reference is reported in the error.
+pub fn render(text: &str) -> io::Result<String> {
+    let mut output = String::new();
+    let mut errors = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|e| start + e) else {
+            break;
+        };
+        let reference = rest[start + 2..end].trim();
+        output.push_str(&rest[..start]);
+        if reference.starts_with("hc:") {
+            match resolve_reference(reference) {
+                Ok(value) => output.push_str(&value),
+                Err(e) => errors.push(format!("{{{{ {reference} }}}}: {e}")),
+            }
+        } else {
+            output.push_str(&rest[start..end + 2]);
+        }
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    if !errors.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("Unresolved references:\n  {}", errors.join("\n  ")),
+        ));
+    }
+    Ok(output)
+}
+
+/// The backup whose file the output symlink points to: the backup that
+/// was given, or else any backup with an entry for the output path.
+/// A symlink to anything else is not written through.
+fn symlinked_backup(
+    output_path: &str,
+    target: &Path,
+    add_backup_name: Option<&str>,
+) -> io::Result<String> {
+    let backup_names: Vec<String> = match add_backup_name {
+        Some(backup_name) => vec![backup_name.to_string()],
+        None => load_config()?.backups.keys().cloned().collect(),
+    };
+    for backup_name in backup_names {
+        let backup_file = get_backup_file(&backup_name, output_path).and_then(fs::canonicalize);
+        if backup_file.is_ok_and(|file| file == target) {
+            return Ok(backup_name);
+        }
+    }
+    let not = match add_backup_name {
+        Some(backup_name) => format!("its file in backup '{backup_name}'"),
+        None => "a backed up file".to_string(),
+    };
+    Err(io::Error::new(
+        ErrorKind::InvalidInput,
+        format!("{output_path} is a symlink to {target:?}, which is not {not}. Remove it first to render there."),
+    ))
+}
+
+/// Render the template, returning the result if no output file is given.
+/// When an output file is given, it may also be added to a backup. If the
+/// output file is already the symlink of a backed up file, the backup
+/// file is updated.
+pub fn render_template(
+    template_path: &str,
+    output_path: Option<&str>,
+    add_backup_name: Option<&str>,
+) -> io::Result<Option<String>> {
+    let rendered = render(&fs::read_to_string(template_path)?)?;
+    let Some(output_path) = output_path else {
+        return Ok(Some(rendered));
+    };
+    let output = absolute_path(output_path);
+    let is_symlink = fs::symlink_metadata(&output)
+        .map(|m| m.is_symlink())
+        .unwrap_or(false);
+    let command = format!("render {template_path} -o {output_path}");
+    if is_symlink {
+        let target = fs::canonicalize(&output)?;
+        let backup_name = symlinked_backup(output_path, &target, add_backup_name)?;
+        debug!("updating symlink target: {target:?}");
+        let old = fs::read(&target).ok();
+        let result = record_operation(&backup_name, &command, || {
+            write_backup_file(&backup_name, output_path, rendered.as_bytes())?;
+            record_change(
+                &backup_name,
+                output_path,
+                old.as_deref(),
+                rendered.as_bytes(),
+            )?;
+            refresh_placements(&backup_name, output_path, old.as_deref())
+        });
+        // (without --add, the backup is only known here, so it is audited here)
+        if add_backup_name.is_none() {
+            let mut audits = BackupAudits::default();
+            let entry = resolve_original_path(output_path).display().to_string();
+            audits.touch(&backup_name, "render", &entry, result.is_ok());
+            audits.finish()?;
+        }
+        result?;
+    } else {
+        let write = || -> io::Result<()> {
+            if !pretend(|| format!("write {}", output.display())) {
+                let undo = Undo::write(&output)?;
+                atomic_write(&output, rendered.as_bytes())?;
+                history::record(undo);
+            }
+            Ok(())
+        };
+        match add_backup_name {
+            Some(backup_name) => record_operation(backup_name, &command, || {
+                write()?;
+                add_to_backup(backup_name, output_path)
+            })?,
+            None => write()?,
+        }
+    }
+    Ok(None)
+}
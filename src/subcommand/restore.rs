@@ -1,14 +1,75 @@
-use crate::paths::Paths;
+use crate::paths::{LinkMode, Paths};
 #[allow(unused_imports)]
 use crate::prelude::*;
 
 use crate::config::load_config;
 use crate::confirm::{confirm, ConfirmProps};
+use crate::dotenv::is_dotenv_file_name;
+use crate::dry_run::{is_dry_run, pretend};
+use crate::git::{normalize_remote, remote_url, repo_root};
+use crate::history;
+use crate::paths::{
+    expand_tilde_path, get_backup_dir, get_backup_paths, set_backup_paths, shorten_path,
+};
+use crate::placement::{place, record_placement, refresh_placed, rewrite_blob, Placement};
+use crate::rotation::{note_change, now};
+use crate::schema::validate_text;
+use crate::selector::Selector;
+use crate::transaction::Transaction;
+use prettytable::{format::consts as fmt, Cell, Row, Table};
+use serde::Serialize;
+use serde_json::json;
+use std::env;
 use std::fs::{self, canonicalize, File};
 use std::io::{self, ErrorKind};
-use std::path::Path;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
-pub fn restore_backup(backup_name: &str, copy: bool, overwrite: bool) -> io::Result<()> {
+/// What to do when a regular file is in the way of a restored file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConflictPolicy {
+    /// Ask whether to overwrite it (unless --no-confirm).
+    #[default]
+    Ask,
+    /// Leave it alone, and don't restore the file.
+    Skip,
+    /// Replace it.
+    Overwrite,
+    /// Rename it to a timestamped sibling, then restore the file.
+    Stash,
+    /// Stop the restore with an error.
+    Fail,
+    /// Take its contents into the backup, then restore the file.
+    Adopt,
+}
+
+impl ConflictPolicy {
+    pub fn parse(s: &str) -> Option<ConflictPolicy> {
+        match s {
+            "skip" => Some(ConflictPolicy::Skip),
+            "overwrite" => Some(ConflictPolicy::Overwrite),
+            "stash" => Some(ConflictPolicy::Stash),
+            "fail" => Some(ConflictPolicy::Fail),
+            "adopt" => Some(ConflictPolicy::Adopt),
+            _ => None,
+        }
+    }
+}
+
+/// Restore the selected files in the backup, each in its own link mode,
+/// unless a mode is given, which is then recorded for every file restored.
+/// Either every file is restored, or (on any error) none of them are, and
+/// the report says why.
+///
+/// With a `root`, every file is restored under that directory instead (eg.
+/// a chroot or a machine image being provisioned), and nothing is recorded.
+pub fn restore_backup(
+    backup_name: &str,
+    selector: &Selector,
+    mode: Option<LinkMode>,
+    on_conflict: ConflictPolicy,
+    root: Option<&str>,
+) -> io::Result<RestoreReport> {
     let config = load_config()?;
     let backup_dir = config
         .backups
@@ -25,7 +86,7 @@ pub fn restore_backup(backup_name: &str, copy: bool, overwrite: bool) -> io::Res
     }
 
     let file = File::open(paths_file)?;
-    let paths: Paths = match ron::de::from_reader(file) {
+    let mut paths: Paths = match ron::de::from_reader(file) {
         Ok(paths) => paths,
         Err(_) => {
             return Err(io::Error::new(
@@ -35,48 +96,720 @@ pub fn restore_backup(backup_name: &str, copy: bool, overwrite: bool) -> io::Res
         }
     };
 
-    for (original_path, backup_path) in paths.files {
-        let original = Path::new(&original_path);
-        let backup_path = Path::new(backup_dir).join(Path::new(&backup_path));
-        //debug!("backup_path: {backup_path:?}");
-        let backup = canonicalize(backup_path).expect("could not get absolute path");
-
-        // Check if the original path is a symlink and if it already points to the correct backup:
-        if original.exists() {
-            if original.is_symlink() {
-                let link_target = fs::read_link(original).unwrap();
-                if link_target == backup {
-                    info!("Valid symlink already exists: {}", original.display());
-                    continue;
-                }
-            } else if !overwrite {
-                // Check if the original path exists and handle overwrite logic
-                match confirm(ConfirmProps {
-                    message: format!("File {} already exists. Overwrite?", original.display()),
-                    ..Default::default()
-                }) {
-                    Ok(true) => (),        // User chose to overwrite
-                    Ok(false) => continue, // User chose not to overwrite
-                    Err(_) => {
-                        return Err(io::Error::new(
-                            ErrorKind::Interrupted,
-                            "Prompt was cancelled or failed",
-                        ))
-                    }
+    let backup_dir = canonicalize(backup_dir)?;
+    let root = root.map(restore_root).transpose()?;
+    if root.is_some() && on_conflict == ConflictPolicy::Adopt {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "Files cannot be adopted into the backup from another root",
+        ));
+    }
+    if root.is_some() {
+        // (a relative symlink resolves the same inside the root as outside)
+        paths.relative_links = true;
+    }
+    let mut steps = Vec::new();
+    for (original_path, backup_path) in &paths.files {
+        if !selector.matches(&paths, original_path) {
+            continue;
+        }
+        let original = match &root {
+            Some(root) => rooted_path(root, original_path),
+            None => PathBuf::from(original_path),
+        };
+        // (a missing backup file is reported by `validate_steps`)
+        let backup = backup_dir.join(backup_path);
+        let mut recorded = paths.mode(original_path);
+        let mut file_mode = restore_mode(recorded, mode, &original, &backup);
+        if let Some(root) = &root {
+            // A symlink out of the root would dangle once it is booted:
+            if file_mode == LinkMode::Symlink && !backup.starts_with(root) {
+                file_mode = LinkMode::Copy;
+            }
+            // (nothing is recorded about the files under the root)
+            recorded = file_mode;
+        }
+        steps.push(plan_step(
+            &paths,
+            original_path,
+            &original,
+            &backup,
+            recorded,
+            file_mode,
+            on_conflict,
+        )?);
+    }
+
+    if steps.is_empty() && !selector.is_all() {
+        let error = "No files in the backup match".to_string();
+        return Ok(RestoreReport::failed(backup_name, error));
+    }
+    let is_rooted = root.is_some();
+    run_steps(
+        backup_name,
+        &mut paths,
+        &mut steps,
+        root.as_deref(),
+        |paths, _| match is_rooted {
+            true => Ok(()),
+            false => set_backup_paths(backup_name, paths),
+        },
+    )
+}
+
+/// The directory to restore into, which must exist already.
+fn restore_root(root: &str) -> io::Result<PathBuf> {
+    match canonicalize(root) {
+        Ok(root) if root.is_dir() => Ok(root),
+        _ => Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("The root directory does not exist: {root}"),
+        )),
+    }
+}
+
+/// The original path of a file, under another root directory.
+fn rooted_path(root: &Path, original_path: &str) -> PathBuf {
+    let original = expand_tilde_path(original_path).expect("failed to expand tilde");
+    root.join(original.strip_prefix("/").unwrap_or(&original))
+}
+
+/// The permissions to create a directory under the root with: those of
+/// the same directory outside of it (so eg. `~/.ssh` stays private), or
+/// else the usual `rwxr-xr-x`.
+fn rooted_dir_mode(root: &Path, dir: &Path) -> Option<u32> {
+    let outside = Path::new("/").join(dir.strip_prefix(root).ok()?);
+    match fs::metadata(outside) {
+        Ok(m) if m.is_dir() => Some(m.permissions().mode() & 0o7777),
+        _ => Some(0o755),
+    }
+}
+
+/// The mode to restore a file in: the requested mode, if any, or else
+/// the recorded one. A valid symlink is left alone in any case.
+fn restore_mode(
+    recorded: LinkMode,
+    requested: Option<LinkMode>,
+    original: &Path,
+    backup: &Path,
+) -> LinkMode {
+    match requested {
+        Some(_)
+            if recorded == LinkMode::Symlink
+                && Placement::of(recorded, original, backup).is_current() =>
+        {
+            recorded
+        }
+        Some(mode) => mode,
+        None => recorded,
+    }
+}
+
+/// A sibling path to move a conflicting file to, named by the time.
+fn stash_path(original: &Path) -> PathBuf {
+    let name = original
+        .file_name()
+        .expect("failed file_name()")
+        .to_string_lossy();
+    let stash = original.with_file_name(format!("{name}.{}.stash", now()));
+    let mut path = stash.clone();
+    let mut n = 1;
+    while fs::symlink_metadata(&path).is_ok() {
+        path = PathBuf::from(format!("{}-{n}", stash.display()));
+        n += 1;
+    }
+    path
+}
+
+/// What restoring one file will do.
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Keep,           // it is already placed correctly
+    Skip,           // a conflicting file is left alone
+    Ask,            // a conflicting file is replaced, if the user agrees
+    Place,          // nothing is in the way
+    Replace,        // a symlink is replaced
+    Overwrite,      // a conflicting file is replaced
+    Stash(PathBuf), // a conflicting file is moved out of the way first
+    Adopt,          // the contents of a conflicting file go into the backup first
+    Fail,           // a conflicting file stops the whole restore
+}
+
+/// One file to restore.
+#[derive(Debug)]
+struct Step {
+    key: String, // the entry whose backup file is restored
+    original: PathBuf,
+    backup: PathBuf,
+    mode: LinkMode,
+    action: Action,
+}
+
+impl Step {
+    fn describe(&self) -> String {
+        let mode = self.mode;
+        match &self.action {
+            Action::Keep => format!("keep the existing {mode}"),
+            Action::Skip => "skip (a conflicting file is in the way)".to_string(),
+            Action::Fail => "fail (a conflicting file is in the way)".to_string(),
+            Action::Ask => format!("ask whether to replace the existing file with a {mode}"),
+            Action::Place => format!("create a {mode}"),
+            Action::Replace => format!("replace the existing symlink with a {mode}"),
+            Action::Overwrite => format!("replace the existing file with a {mode}"),
+            Action::Stash(stash) => format!(
+                "move the existing file to {}, and create a {mode}",
+                stash.display()
+            ),
+            Action::Adopt => {
+                format!("adopt the existing file into the backup, and create a {mode}")
+            }
+        }
+    }
+
+    fn is_restored(&self) -> bool {
+        !matches!(self.action, Action::Skip | Action::Ask | Action::Fail)
+    }
+
+    /// What the step does (or did) to the file.
+    fn outcome(&self) -> Outcome {
+        match self.action {
+            Action::Keep => Outcome::AlreadyValid,
+            Action::Skip => Outcome::Skipped,
+            Action::Fail => Outcome::Failed,
+            // (only planned: the user is asked first)
+            Action::Ask | Action::Overwrite => Outcome::Overwritten,
+            Action::Stash(_) => Outcome::Stashed,
+            Action::Adopt => Outcome::Adopted,
+            Action::Place | Action::Replace => match self.mode {
+                LinkMode::Symlink => Outcome::Linked,
+                LinkMode::Hardlink => Outcome::Hardlinked,
+                LinkMode::Copy => Outcome::Copied,
+            },
+        }
+    }
+}
+
+/// What happened to one file in a restore.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Linked,
+    Hardlinked,
+    Copied,
+    AlreadyValid,
+    Overwritten, // a conflicting file was replaced
+    Stashed,     // a conflicting file was moved aside first
+    Adopted,     // a conflicting file was taken into the backup first
+    Skipped,
+    Failed,
+    NotRestored, // because another file failed
+}
+
+impl Outcome {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Outcome::Linked => "linked",
+            Outcome::Hardlinked => "hardlinked",
+            Outcome::Copied => "copied",
+            Outcome::AlreadyValid => "already valid",
+            Outcome::Overwritten => "overwritten",
+            Outcome::Stashed => "stashed",
+            Outcome::Adopted => "adopted",
+            Outcome::Skipped => "skipped",
+            Outcome::Failed => "failed",
+            Outcome::NotRestored => "not restored",
+        }
+    }
+}
+
+/// The result of restoring one file.
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    pub outcome: Outcome,
+    pub mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>, // the plan, in a dry run, or why it failed
+}
+
+/// What a restore did (or, in a dry run, would do) to each file.
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub backup_name: String,
+    pub dry_run: bool,
+    pub files: Vec<FileReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // why nothing was restored
+}
+
+impl RestoreReport {
+    /// Report the steps as done (or planned), or else as failed: `problems`
+    /// are the indexes of the failed steps, with the reasons.
+    fn new(
+        backup_name: &str,
+        steps: &[Step],
+        problems: &[(usize, String)],
+        error: Option<String>,
+    ) -> Self {
+        let dry_run = is_dry_run();
+        let files = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let reasons: Vec<&str> = problems
+                    .iter()
+                    .filter(|(p, _)| *p == i)
+                    .map(|(_, reason)| reason.as_str())
+                    .collect();
+                let (outcome, detail) = if !reasons.is_empty() {
+                    (Outcome::Failed, Some(reasons.join("; ")))
+                } else if error.is_some() {
+                    (Outcome::NotRestored, None)
+                } else if dry_run {
+                    (step.outcome(), Some(step.describe()))
+                } else {
+                    let stash = match &step.action {
+                        Action::Stash(stash) => Some(format!("moved to {}", stash.display())),
+                        _ => None,
+                    };
+                    (step.outcome(), stash)
+                };
+                FileReport {
+                    path: step.original.display().to_string(),
+                    outcome,
+                    mode: step.mode.to_string(),
+                    detail,
                 }
+            })
+            .collect();
+        RestoreReport {
+            backup_name: backup_name.to_string(),
+            dry_run,
+            files,
+            error,
+        }
+    }
+
+    /// Report that nothing was restored, before any file was considered.
+    pub fn failed(backup_name: &str, error: String) -> Self {
+        RestoreReport::new(backup_name, &[], &[], Some(error))
+    }
+
+    /// The number of files with each outcome, in the order of `Outcome`.
+    pub fn summary(&self) -> Vec<(Outcome, usize)> {
+        let mut summary: Vec<(Outcome, usize)> = Vec::new();
+        for file in &self.files {
+            match summary.iter_mut().find(|(o, _)| *o == file.outcome) {
+                Some((_, n)) => *n += 1,
+                None => summary.push((file.outcome, 1)),
             }
         }
+        summary.sort_by_key(|(o, _)| *o as u8);
+        summary
+    }
+
+    /// The number of files restored (or left as they were, already valid).
+    pub fn restored(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| !matches!(f.outcome, Outcome::Skipped))
+            .count()
+    }
+
+    /// Print a table of the files and a summary line, or JSON.
+    pub fn print(&self, output_as_json: bool) {
+        if output_as_json {
+            let summary: serde_json::Map<String, serde_json::Value> = self
+                .summary()
+                .into_iter()
+                .map(|(outcome, n)| (json!(outcome).as_str().unwrap().to_string(), json!(n)))
+                .collect();
+            let mut json_output = json!(self);
+            json_output["summary"] = json!(summary);
+            println!("{}", serde_json::to_string_pretty(&json_output).unwrap());
+            return;
+        }
+        if self.files.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table.set_format(*fmt::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        let titles = match self.dry_run {
+            true => ["Path", "Plan", "Mode", "Details"],
+            false => ["Path", "Result", "Mode", "Details"],
+        };
+        table.set_titles(Row::new(titles.iter().map(|x| Cell::new(x)).collect()));
+        for file in &self.files {
+            table.add_row(row![
+                shorten_path(&file.path),
+                file.outcome.describe(),
+                file.mode,
+                file.detail.as_deref().unwrap_or("")
+            ]);
+        }
+        table.printstd();
+        let summary: Vec<String> = self
+            .summary()
+            .iter()
+            .map(|(outcome, n)| format!("{n} {}", outcome.describe()))
+            .collect();
+        println!("{}", summary.join(", "));
+    }
+}
+
+/// Decide what to do to restore one file (of the entry `key`), without
+/// changing anything yet. `recorded` is the mode it was placed in before.
+fn plan_step(
+    paths: &Paths,
+    key: &str,
+    original: &Path,
+    backup: &Path,
+    recorded: LinkMode,
+    mode: LinkMode,
+    on_conflict: ConflictPolicy,
+) -> io::Result<Step> {
+    // Check if the original path is already placed correctly:
+    let status = Placement::of(mode, original, backup);
+    // (in the style of symlink that the backup wants)
+    let wanted_link = match paths.wants_relative_links() {
+        true => Placement::RelativeLink,
+        false => Placement::AbsoluteLink,
+    };
+    // (and a file is only a copy if it was placed as one)
+    let valid = match mode {
+        LinkMode::Symlink => status == wanted_link,
+        LinkMode::Hardlink => status.is_current(),
+        LinkMode::Copy => status.is_current() && recorded == LinkMode::Copy,
+    };
+    let action = if valid {
+        Action::Keep
+    } else if original.exists() && !original.is_symlink() {
+        match on_conflict {
+            ConflictPolicy::Ask => Action::Ask,
+            ConflictPolicy::Skip => Action::Skip,
+            ConflictPolicy::Overwrite => Action::Overwrite,
+            ConflictPolicy::Stash => Action::Stash(stash_path(original)),
+            ConflictPolicy::Adopt => Action::Adopt,
+            ConflictPolicy::Fail => Action::Fail,
+        }
+    } else if fs::symlink_metadata(original).is_ok() {
+        Action::Replace
+    } else {
+        Action::Place
+    };
+    Ok(Step {
+        key: key.to_string(),
+        original: original.to_path_buf(),
+        backup: backup.to_path_buf(),
+        mode,
+        action,
+    })
+}
 
-        // Copy or create a symlink based on the --copy flag
-        if copy {
-            fs::copy(backup, original)?;
-        } else {
-            if original.exists() {
-                fs::remove_file(original)?; // Remove the existing file if it exists
+/// Check that every step can be carried out, before any of them are.
+/// Returns the problems found, with the indexes of their steps.
+fn validate_steps(backup_name: &str, steps: &[Step]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let original = step.original.display();
+        if step.action == Action::Fail {
+            problems.push((i, format!("A conflicting file exists at {original}")));
+        }
+        // (even a file that looks placed already, as a dangling symlink may)
+        if step.is_restored() && fs::File::open(&step.backup).is_err() {
+            problems.push((
+                i,
+                format!(
+                    "the backup file of {original} cannot be read: {}",
+                    step.backup.display()
+                ),
+            ));
+        }
+    }
+    for (i, step) in steps
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.action != Action::Keep && s.is_restored())
+    {
+        let mut problem = |p: String| problems.push((i, p));
+        let original = step.original.display();
+        let parent = step
+            .original
+            .parent()
+            .expect("failed to get parent directory");
+        // (missing directories are created, unless a file is in the way)
+        let existing = parent
+            .ancestors()
+            .find(|d| d.exists())
+            .expect("failed to find an existing parent directory");
+        if !existing.is_dir() {
+            problem(format!("{} is not a directory", existing.display()));
+        }
+        if fs::symlink_metadata(&step.original).is_ok_and(|m| m.is_dir()) {
+            problem(format!("a directory is in the way: {original}"));
+        }
+        // A copy is written over a conflicting file, so it must be writable:
+        let write_protected = fs::symlink_metadata(&step.original)
+            .is_ok_and(|m| m.is_file() && m.permissions().readonly());
+        let overwrites = matches!(step.action, Action::Overwrite | Action::Adopt);
+        if step.mode == LinkMode::Copy && overwrites && write_protected {
+            problem(format!("Permission denied: {original} is write-protected"));
+        }
+        if step.mode == LinkMode::Hardlink {
+            let dev = |path: &Path| fs::metadata(path).map(|m| m.dev()).ok();
+            if dev(existing) != dev(&step.backup) {
+                problem(format!(
+                    "{original} is not on the same filesystem as the backup"
+                ));
+            }
+        }
+        if step.action == Action::Adopt && is_dotenv_file_name(&step.key) {
+            let valid = fs::read_to_string(&step.original)
+                .and_then(|text| validate_text(backup_name, &step.key, &text));
+            if let Err(e) = valid {
+                problem(format!("{original} cannot be adopted: {e}"));
             }
-            std::os::unix::fs::symlink(backup, original)?;
         }
     }
+    problems
+}
+
+/// An entry whose contents were adopted, with its contents before.
+type Adoption = (String, Vec<u8>);
 
+/// Carry out the steps, and then refresh the other placements of the
+/// adopted entries. On failure, returns the index of the step that failed.
+fn apply_steps(
+    paths: &mut Paths,
+    steps: &[Step],
+    root: Option<&Path>,
+    tx: &mut Transaction,
+) -> Result<(), (Option<usize>, io::Error)> {
+    let mut adopted = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        if let Some(adoption) = apply_step(paths, step, root, tx).map_err(|e| (Some(i), e))? {
+            adopted.push((i, adoption));
+        }
+    }
+    // Other hard links and copies of adopted files follow the backup:
+    for (i, (key, old)) in &adopted {
+        let backup_dir = steps[*i]
+            .backup
+            .parent()
+            .expect("failed to get backup directory");
+        refresh_placed(paths, backup_dir, key, Some(old), tx).map_err(|e| (Some(*i), e))?;
+    }
     Ok(())
 }
+
+/// Carry out one step. Returns the entry, if the contents of the file were
+/// adopted.
+fn apply_step(
+    paths: &mut Paths,
+    step: &Step,
+    root: Option<&Path>,
+    tx: &mut Transaction,
+) -> io::Result<Option<Adoption>> {
+    let original = step.original.as_path();
+    let backup_dir = step
+        .backup
+        .parent()
+        .expect("failed to get backup directory");
+    // (an earlier step may have moved the entry to another blob)
+    let mut backup = backup_dir.join(&paths.files[&step.key]);
+    let mut adopted = None;
+    match &step.action {
+        Action::Keep => {
+            debug!("Valid {} already exists: {}", step.mode, original.display());
+            let original = original.to_str().expect("failed to_str on path");
+            record_placement(paths, original, step.mode, &backup)?;
+            return Ok(None);
+        }
+        Action::Skip | Action::Ask | Action::Fail => {
+            debug!("Skipped existing file: {}", original.display());
+            return Ok(None);
+        }
+        Action::Stash(stash) => {
+            tx.rename(original, stash)?;
+            debug!(
+                "Moved the existing file {} to {}",
+                original.display(),
+                stash.display()
+            );
+        }
+        Action::Adopt => {
+            let contents = fs::read(original)?;
+            let old = fs::read(&backup)?;
+            if contents != old {
+                backup = rewrite_blob(paths, backup_dir, &step.key, &contents, tx)?;
+                note_change(paths, &step.key, Some(&old), &contents);
+                adopted = Some((step.key.clone(), old));
+                debug!(
+                    "Adopted the contents of {} into the backup.",
+                    original.display()
+                );
+            }
+        }
+        Action::Place | Action::Replace | Action::Overwrite => (),
+    }
+    let parent = original.parent().expect("failed to get parent directory");
+    match root {
+        Some(root) => tx.create_dir_all_with_mode(parent, |d| rooted_dir_mode(root, d))?,
+        None => tx.create_dir_all(parent)?,
+    }
+    if fs::symlink_metadata(original).is_ok() {
+        tx.set_aside(original)?;
+    }
+    tx.create(original);
+    place(paths, step.mode, &backup, original)?;
+    Ok(adopted)
+}
+
+/// Ask about any conflicts, check, and then carry out the steps, all or
+/// nothing, ending with `save`, which records them. In a dry run, the
+/// steps are only checked. Returns a report of what was (or would be)
+/// done, or of the problems that stopped it.
+fn run_steps(
+    backup_name: &str,
+    paths: &mut Paths,
+    steps: &mut [Step],
+    root: Option<&Path>,
+    save: impl FnOnce(Paths, &[Step]) -> io::Result<()>,
+) -> io::Result<RestoreReport> {
+    if !is_dry_run() {
+        for step in steps.iter_mut().filter(|s| s.action == Action::Ask) {
+            // Check if the original path exists and handle overwrite logic
+            step.action = match confirm(ConfirmProps {
+                message: format!(
+                    "File {} already exists. Overwrite?",
+                    step.original.display()
+                ),
+                ..Default::default()
+            }) {
+                Ok(true) => Action::Overwrite, // User chose to overwrite
+                Ok(false) => Action::Skip,     // User chose not to overwrite
+                Err(_) => {
+                    return Err(io::Error::new(
+                        ErrorKind::Interrupted,
+                        "Prompt was cancelled or failed",
+                    ))
+                }
+            };
+        }
+    }
+    let problems = validate_steps(backup_name, steps);
+    if !problems.is_empty() {
+        let list: Vec<&str> = problems.iter().map(|(_, p)| p.as_str()).collect();
+        let error = format!("Nothing was restored:\n  {}", list.join("\n  "));
+        return Ok(RestoreReport::new(
+            backup_name,
+            steps,
+            &problems,
+            Some(error),
+        ));
+    }
+    let restoring = || format!("restore {} file(s) as planned", steps.len());
+    if pretend(restoring) {
+        return Ok(RestoreReport::new(backup_name, steps, &[], None));
+    }
+    let mut tx = Transaction::new();
+    let result = apply_steps(paths, steps, root, &mut tx)
+        .and_then(|()| save(std::mem::take(paths), steps).map_err(|e| (None, e)));
+    match result {
+        Ok(()) => {
+            let mut report = RestoreReport::new(backup_name, steps, &[], None);
+            match tx.commit() {
+                Ok(undo) => undo.into_iter().for_each(history::record),
+                Err(e) => {
+                    report.error = Some(format!(
+                        "The files were restored, but the files they replaced could not be removed: {e}"
+                    ))
+                }
+            }
+            Ok(report)
+        }
+        Err((i, e)) => {
+            let error = match tx.rollback() {
+                Ok(()) => format!("{e} (every restored file was rolled back)"),
+                Err(r) => format!("{e} (and the rollback failed: {r})"),
+            };
+            let problems: Vec<(usize, String)> =
+                i.map(|i| (i, e.to_string())).into_iter().collect();
+            Ok(RestoreReport::new(
+                backup_name,
+                steps,
+                &problems,
+                Some(error),
+            ))
+        }
+    }
+}
+
+/// Restore the repository relative files of a backup into the git
+/// repository containing the current directory. New paths are recorded
+/// as aliases.
+pub fn restore_here(
+    backup_name: &str,
+    selector: &Selector,
+    mode: Option<LinkMode>,
+    on_conflict: ConflictPolicy,
+) -> io::Result<RestoreReport> {
+    let cwd = env::current_dir()?;
+    let root = canonicalize(repo_root(&cwd)?)?;
+    let remote = normalize_remote(&remote_url(&cwd)?);
+    debug!("restoring into {root:?} for remote {remote}");
+    let backup_dir = canonicalize(get_backup_dir(backup_name)?)?;
+    let mut paths = get_backup_paths(backup_name)?;
+    let entries: Vec<(String, String)> = paths
+        .meta
+        .iter()
+        .filter_map(|(key, meta)| match &meta.repo {
+            Some(repo) if repo.remote == remote => Some((key.clone(), repo.path.clone())),
+            _ => None,
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("No files in backup '{backup_name}' belong to the repository {remote}"),
+        ));
+    }
+    let mut steps = Vec::new();
+    for (key, repo_path) in entries {
+        let original = root.join(&repo_path);
+        if !selector.matches_at(&paths, &key, &original) {
+            continue;
+        }
+        let backup = backup_dir.join(&paths.files[&key]);
+        let original_str = original.to_str().expect("failed to_str on path");
+        let recorded = match paths.files.contains_key(original_str) {
+            true => paths.mode(original_str),
+            false => paths.mode(&key),
+        };
+        let file_mode = restore_mode(recorded, mode, &original, &backup);
+        steps.push(plan_step(
+            &paths,
+            &key,
+            &original,
+            &backup,
+            recorded,
+            file_mode,
+            on_conflict,
+        )?);
+    }
+    run_steps(
+        backup_name,
+        &mut paths,
+        &mut steps,
+        None,
+        |mut paths, steps| {
+            for step in steps.iter().filter(|s| s.is_restored()) {
+                let original = step.original.to_str().expect("failed to_str on path");
+                if !paths.files.contains_key(original) {
+                    paths.insert_alias(&step.key, original);
+                }
+            }
+            set_backup_paths(backup_name, paths)
+        },
+    )
+}
@@ -0,0 +1,54 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::dotenv::{is_dotenv_file_name, DotEnv};
+use crate::paths::{get_backup_file, get_backup_paths};
+use indexmap::IndexMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// Load and merge the variables from the given tracked dotenv files, in
+/// order, so that later files override earlier ones. If no files are
+/// given, every tracked file that looks like a dotenv file is used.
+pub fn load_env_files(backup_name: &str, files: &[String]) -> io::Result<IndexMap<String, String>> {
+    let files: Vec<String> = if files.is_empty() {
+        get_backup_paths(backup_name)?
+            .files
+            .keys()
+            .filter(|f| is_dotenv_file_name(f))
+            .cloned()
+            .collect()
+    } else {
+        files.to_vec()
+    };
+    if files.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("No dotenv files found in backup: {backup_name}"),
+        ));
+    }
+    let mut vars = IndexMap::new();
+    for file in files {
+        let backup_file = get_backup_file(backup_name, &file)?;
+        debug!("loading env file: {file} ({backup_file:?})");
+        let dotenv = DotEnv::parse(&fs::read_to_string(backup_file)?)?;
+        for entry in dotenv.entries() {
+            vars.insert(entry.key.clone(), entry.value.clone());
+        }
+    }
+    Ok(vars)
+}
+
+/// Replace this process with the command, with the variables of the
+/// dotenv files added to its environment. This only returns on error.
+pub fn run_with_env(backup_name: &str, files: &[String], command: &[String]) -> io::Result<()> {
+    let vars = load_env_files(backup_name, files)?;
+    let (program, args) = command.split_first().ok_or(io::Error::new(
+        ErrorKind::InvalidInput,
+        "No command was given",
+    ))?;
+    debug!("running: {program:?} {args:?}");
+    Err(Command::new(program).args(args).envs(vars).exec())
+}
@@ -0,0 +1,121 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::config::load_config;
+use crate::dotenv::{is_dotenv_file_name, DotEnv};
+use crate::paths::{get_backup_dir, get_backup_paths, shorten_path};
+use crate::rotation::now;
+use prettytable::{format::consts as fmt, Cell, Row, Table};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, ErrorKind};
+
+const DAY: u64 = 60 * 60 * 24;
+
+/// A file, or a key in a dotenv file, that is older than the policy allows.
+#[derive(Debug, Serialize)]
+pub struct StaleSecret {
+    pub backup_name: String,
+    pub path: String,
+    pub key: Option<String>,
+    pub changed: Option<u64>, // unknown if it changed before it was tracked
+    pub age_days: Option<u64>,
+    pub max_age_days: u64,
+}
+
+/// Find every secret older than the max-age policy of its backup, in the
+/// named backup or in all backups. The `max_age_days` argument overrides
+/// the policy. Backups without any policy are skipped.
+pub fn find_stale(
+    backup_name: Option<&str>,
+    max_age_days: Option<u64>,
+) -> io::Result<Vec<StaleSecret>> {
+    let backups: Vec<String> = match backup_name {
+        Some(backup_name) => vec![backup_name.to_string()],
+        None => load_config()?.backups.keys().cloned().collect(),
+    };
+    let now = now();
+    let mut stale = Vec::new();
+    for backup in backups {
+        let paths = match get_backup_paths(&backup) {
+            Err(e) if e.kind() == ErrorKind::NotFound && backup_name.is_none() => continue,
+            paths => paths?,
+        };
+        let Some(max_age_days) = max_age_days.or(paths.max_age_days) else {
+            debug!("backup {backup} has no max-age policy");
+            continue;
+        };
+        let backup_dir = get_backup_dir(&backup)?;
+        let mut check = |path: &str, key: Option<&str>, changed: Option<u64>| {
+            let age_days = changed.map(|c| now.saturating_sub(c) / DAY);
+            if age_days.is_none_or(|age| age >= max_age_days) {
+                stale.push(StaleSecret {
+                    backup_name: backup.clone(),
+                    path: path.to_string(),
+                    key: key.map(|k| k.to_string()),
+                    changed,
+                    age_days,
+                    max_age_days,
+                });
+            }
+        };
+        for (original_path, id) in &paths.files {
+            // Aliases share the contents of their primary entry:
+            if paths.primary(original_path) != original_path {
+                continue;
+            }
+            let meta = paths.meta.get(original_path).cloned().unwrap_or_default();
+            let dotenv = match is_dotenv_file_name(original_path) {
+                true => fs::read_to_string(backup_dir.join(id))
+                    .ok()
+                    .and_then(|text| DotEnv::parse(&text).ok()),
+                false => None,
+            };
+            match dotenv {
+                Some(dotenv) => {
+                    for entry in dotenv.entries() {
+                        let changed = meta.keys_changed.get(&entry.key).copied();
+                        check(original_path, Some(&entry.key), changed.or(meta.changed));
+                    }
+                }
+                None => check(original_path, None, meta.changed),
+            }
+        }
+    }
+    Ok(stale)
+}
+
+/// Print the stale secrets. Returns true if there were any.
+pub fn report_stale(
+    backup_name: Option<&str>,
+    max_age_days: Option<u64>,
+    output_as_json: bool,
+) -> io::Result<bool> {
+    let stale = find_stale(backup_name, max_age_days)?;
+    if output_as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "stale": stale })).unwrap()
+        );
+    } else if !stale.is_empty() {
+        let mut table = Table::new();
+        table.set_format(*fmt::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(Row::new(
+            ["Backup Name", "Path", "Key", "Age (days)", "Max Age"]
+                .iter()
+                .map(|x| Cell::new(x))
+                .collect(),
+        ));
+        for s in &stale {
+            table.add_row(row![
+                s.backup_name,
+                shorten_path(&s.path),
+                s.key.as_deref().unwrap_or(""),
+                s.age_days.map_or("unknown".to_string(), |a| a.to_string()),
+                s.max_age_days
+            ]);
+        }
+        table.printstd();
+    }
+    Ok(!stale.is_empty())
+}
@@ -0,0 +1,61 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::paths::{get_backup_dir, get_backup_paths, shorten_path};
+use crate::placement::Placement;
+use prettytable::{format::consts as fmt, Cell, Row, Table};
+use serde_json::json;
+use std::io;
+use std::path::Path;
+
+/// Print the state of every file in the backup, and warn if the backup
+/// has both absolute and relative symlinks.
+pub fn backup_status(backup_name: &str, output_as_json: bool) -> io::Result<()> {
+    let backup_dir = get_backup_dir(backup_name)?;
+    let paths = get_backup_paths(backup_name)?;
+    let files: Vec<(&String, Placement)> = paths
+        .files
+        .iter()
+        .map(|(original, id)| {
+            let mode = paths.mode(original);
+            let status = Placement::of(mode, Path::new(original), &backup_dir.join(id));
+            (original, status)
+        })
+        .collect();
+    let has = |status| files.iter().any(|(_, s)| *s == status);
+    let mixed_links = has(Placement::AbsoluteLink) && has(Placement::RelativeLink);
+    if output_as_json {
+        let json_files: Vec<serde_json::Value> = files
+            .iter()
+            .map(|(path, status)| json!({"path": path, "status": status}))
+            .collect();
+        let json_output = json!({
+            "backup_name": backup_name,
+            "files": json_files,
+            "mixed_links": mixed_links,
+        });
+        println!("{}", serde_json::to_string_pretty(&json_output).unwrap());
+    } else {
+        let mut table = Table::new();
+        table.set_format(*fmt::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.set_titles(Row::new(
+            ["Path", "Status"].iter().map(|x| Cell::new(x)).collect(),
+        ));
+        for (path, status) in &files {
+            table.add_row(row![shorten_path(path), status.describe()]);
+        }
+        table.printstd();
+    }
+    if mixed_links {
+        let style = match paths.wants_relative_links() {
+            true => "relative",
+            false => "absolute",
+        };
+        warn!(
+            "Backup '{backup_name}' has both absolute and relative symlinks. \
+             Run `{} restore {backup_name}` to make them all {style}.",
+            env!("CARGO_BIN_NAME")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,202 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::audit::BackupAudits;
+use crate::dry_run;
+use crate::git::repo_path;
+use crate::history::record_operation;
+use crate::paths::{
+    get_backup_dir, get_backup_entry, get_backup_paths, resolve_original_path, set_backup_paths,
+};
+use crate::placement::{place_undoable, Placement};
+use crate::project::{find_project_file, load_project, PROJECT_FILE};
+use crate::subcommand::add::{add_repo_relative, add_to_backup};
+use std::env;
+use std::fs::{self, canonicalize};
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+/// What `sync` did (or could not do) for one declared file.
+#[derive(Debug, PartialEq)]
+enum Synced {
+    InSync,
+    Added,
+    Linked,
+    Missing,
+    Conflict(&'static str),
+}
+
+/// The path of a file declared in the project file, which must be
+/// relative to the project directory and stay inside it, also when
+/// symlinked directories are followed.
+fn project_path(project_dir: &Path, relative_path: &str) -> io::Result<PathBuf> {
+    let outside = || {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{relative_path} is outside the project directory (in {PROJECT_FILE})"),
+        )
+    };
+    let mut path = project_dir.to_path_buf();
+    for component in Path::new(relative_path).components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir => (),
+            Component::ParentDir if path != project_dir => {
+                path.pop();
+            }
+            _ => return Err(outside()),
+        }
+    }
+    if path == project_dir {
+        return Err(outside());
+    }
+    let path = resolve_original_path(path.to_str().expect("failed to_str on path"));
+    // (the file itself is usually a symlink into the backup)
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .find(|d| d.exists())
+        .expect("failed to find an existing directory");
+    if !canonicalize(dir)?.starts_with(project_dir) {
+        return Err(outside());
+    }
+    Ok(path)
+}
+
+fn sync_file(backup_name: &str, path: &Path) -> io::Result<Synced> {
+    let path_str = path.to_str().expect("failed to_str on path");
+    let metadata = fs::symlink_metadata(path).ok();
+
+    // Tracked at this very path:
+    if let Ok((key, backup_file)) = get_backup_entry(backup_name, path_str) {
+        let mut paths = get_backup_paths(backup_name)?;
+        let mode = paths.mode(&key);
+        return match metadata {
+            None => {
+                if let Some(parent) = path.parent() {
+                    dry_run::create_dir_all(parent)?;
+                }
+                place_undoable(&mut paths, mode, &backup_file, path)?;
+                set_backup_paths(backup_name, paths)?;
+                Ok(Synced::Linked)
+            }
+            Some(_) if Placement::of(mode, path, &backup_file).is_current() => Ok(Synced::InSync),
+            Some(_) => Ok(Synced::Conflict("a different file is in the way")),
+        };
+    }
+
+    // Tracked in another clone of the same repository:
+    let repo_path = repo_path(path).ok();
+    if let Some(repo_path) = &repo_path {
+        let mut paths = match get_backup_paths(backup_name) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Default::default(),
+            paths => paths?,
+        };
+        let key = paths
+            .meta
+            .iter()
+            .find(|(_, m)| m.repo.as_ref() == Some(repo_path))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = key {
+            if metadata.is_some() {
+                return Ok(Synced::Conflict(
+                    "the backup has this file from another clone, but a different file is in the way",
+                ));
+            }
+            let backup_file = get_backup_dir(backup_name)?.join(&paths.files[&key]);
+            if let Some(parent) = path.parent() {
+                dry_run::create_dir_all(parent)?;
+            }
+            dry_run::symlink(&paths, &backup_file, path)?;
+            paths.insert_alias(&key, path_str);
+            set_backup_paths(backup_name, paths)?;
+            return Ok(Synced::Linked);
+        }
+    }
+
+    // Not tracked anywhere:
+    match metadata {
+        Some(m) if m.is_file() => {
+            match repo_path {
+                Some(_) => add_repo_relative(backup_name, path_str)?,
+                None => add_to_backup(backup_name, path_str)?,
+            }
+            Ok(Synced::Added)
+        }
+        Some(_) => Ok(Synced::Conflict("not a regular file")),
+        None => Ok(Synced::Missing),
+    }
+}
+
+/// Find the project file in the current directory (or a parent), and
+/// make the files it declares match the backups. Declared files that
+/// are not backed up yet are added, and backed up files that are
+/// missing are linked into place. Returns false if any declared file
+/// is missing from its backup, or could not be synced.
+pub fn sync_project() -> io::Result<bool> {
+    let cwd = env::current_dir()?;
+    let project_file = find_project_file(&cwd).ok_or(io::Error::new(
+        ErrorKind::NotFound,
+        format!(
+            "No {PROJECT_FILE} found in {} or any parent directory",
+            cwd.display()
+        ),
+    ))?;
+    let project_dir = canonicalize(
+        project_file
+            .parent()
+            .expect("failed to get parent directory"),
+    )?;
+    let project = load_project(&project_file)?;
+    // Make sure every backup and path is valid before changing anything:
+    let mut files = Vec::new();
+    for (relative_path, backup_name) in &project.files {
+        get_backup_dir(backup_name).map_err(|e| {
+            io::Error::new(e.kind(), format!("{e}: {backup_name} (in {PROJECT_FILE})"))
+        })?;
+        files.push((
+            relative_path,
+            backup_name,
+            project_path(&project_dir, relative_path)?,
+        ));
+    }
+    let mut all_synced = true;
+    let mut audits = BackupAudits::default();
+    for (relative_path, backup_name, path) in files {
+        let command = format!("sync {relative_path}");
+        let entry = path.display().to_string();
+        let synced = match record_operation(backup_name, &command, || sync_file(backup_name, &path))
+        {
+            Ok(synced) => synced,
+            Err(e) => {
+                audits.touch(backup_name, "sync", &entry, false);
+                audits.finish()?;
+                return Err(e);
+            }
+        };
+        match synced {
+            Synced::InSync => debug!("in sync: {relative_path}"),
+            // (a dry run printed what it would do instead)
+            Synced::Added | Synced::Linked if dry_run::is_dry_run() => (),
+            Synced::Added => {
+                audits.touch(backup_name, "sync", &entry, true);
+                info!("Added '{relative_path}' to backup '{backup_name}'.")
+            }
+            Synced::Linked => {
+                audits.touch(backup_name, "sync", &entry, true);
+                info!("Linked '{relative_path}' from backup '{backup_name}'.")
+            }
+            Synced::Missing => {
+                all_synced = false;
+                eprintln!("Missing from backup '{backup_name}': {relative_path}");
+            }
+            Synced::Conflict(reason) => {
+                all_synced = false;
+                audits.touch(backup_name, "sync", &entry, false);
+                eprintln!("Could not sync '{relative_path}': {reason}");
+            }
+        }
+    }
+    audits.finish()?;
+    Ok(all_synced)
+}
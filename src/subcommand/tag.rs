@@ -0,0 +1,38 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::paths::{get_backup_paths, set_backup_paths};
+use crate::selector::Selector;
+use std::io::{self, ErrorKind};
+
+/// Add a tag to (or remove it from) the selected files of a backup.
+/// Returns the number of files that were changed.
+pub fn tag_files(
+    backup_name: &str,
+    tag: &str,
+    selector: &Selector,
+    remove: bool,
+) -> io::Result<usize> {
+    let mut paths = get_backup_paths(backup_name)?;
+    let keys: Vec<String> = selector.select(&paths).into_iter().cloned().collect();
+    if keys.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::NotFound,
+            "No files in the backup match",
+        ));
+    }
+    let mut changed = 0;
+    for key in keys {
+        let tags = &mut paths.meta.entry(key).or_default().tags;
+        let has_tag = tags.iter().any(|t| t == tag);
+        if remove && has_tag {
+            tags.retain(|t| t != tag);
+            changed += 1;
+        } else if !remove && !has_tag {
+            tags.push(tag.to_string());
+            changed += 1;
+        }
+    }
+    set_backup_paths(backup_name, paths)?;
+    Ok(changed)
+}
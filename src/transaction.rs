@@ -0,0 +1,222 @@
+#[allow(unused_imports)]
+use crate::prelude::*;
+
+use crate::atomic::atomic_write;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// A change to the filesystem, and how to take it back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Undo {
+    Remove(PathBuf),    // a file that was created
+    RemoveDir(PathBuf), // a directory that was created
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    }, // a file that was moved
+    Write {
+        path: PathBuf,
+        contents: Vec<u8>,
+    },
+    Recreate {
+        path: PathBuf,
+        contents: Vec<u8>,
+        mode: u32,
+    }, // a file that was removed
+    Relink {
+        path: PathBuf,
+        target: PathBuf,
+    }, // a symlink that was removed
+}
+
+impl Undo {
+    /// How to take back removing the file (or symlink) at `path`.
+    pub fn removal(path: &Path) -> io::Result<Undo> {
+        let metadata = fs::symlink_metadata(path)?;
+        Ok(match metadata.is_symlink() {
+            true => Undo::Relink {
+                path: path.to_path_buf(),
+                target: fs::read_link(path)?,
+            },
+            false => Undo::Recreate {
+                path: path.to_path_buf(),
+                contents: fs::read(path)?,
+                mode: metadata.permissions().mode(),
+            },
+        })
+    }
+
+    /// How to take back writing to the file at `path`: by writing its
+    /// contents back, or by removing it, if it is new.
+    pub fn write(path: &Path) -> io::Result<Undo> {
+        match fs::read(path) {
+            Ok(contents) => Ok(Undo::Write {
+                path: path.to_path_buf(),
+                contents,
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Undo::Remove(path.to_path_buf())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The path that the change is taken back at.
+    pub fn path(&self) -> &Path {
+        match self {
+            Undo::Remove(path)
+            | Undo::RemoveDir(path)
+            | Undo::Write { path, .. }
+            | Undo::Recreate { path, .. }
+            | Undo::Relink { path, .. } => path,
+            Undo::Rename { to, .. } => to,
+        }
+    }
+
+    fn apply(&self) -> io::Result<()> {
+        // (not the contents, which may be secret)
+        debug!("undoing a change to {:?}", self.path());
+        match self {
+            Undo::Remove(path) => fs::remove_file(path),
+            Undo::RemoveDir(dir) => fs::remove_dir(dir),
+            Undo::Rename { from, to } => fs::rename(from, to),
+            Undo::Write { path, contents } => atomic_write(path, contents),
+            Undo::Recreate {
+                path,
+                contents,
+                mode,
+            } => {
+                fs::write(path, contents)?;
+                fs::set_permissions(path, fs::Permissions::from_mode(*mode))
+            }
+            Undo::Relink { path, target } => std::os::unix::fs::symlink(target, path),
+        }
+    }
+}
+
+/// Take back every change, newest first.
+pub fn undo_all(undo: impl DoubleEndedIterator<Item = Undo>) -> io::Result<()> {
+    for undo in undo.rev() {
+        match undo.apply() {
+            // (a file may have failed before it was created)
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// A series of filesystem changes that can be rolled back as a whole.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    undo: Vec<Undo>,
+    set_aside: Vec<PathBuf>, // to be deleted once committed
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a directory and any missing parents of it.
+    pub fn create_dir_all(&mut self, dir: &Path) -> io::Result<()> {
+        self.create_dir_all_with_mode(dir, |_| None)
+    }
+
+    /// Create a directory and any missing parents of it, each with the
+    /// permissions that `mode` gives for it (or the default ones).
+    pub fn create_dir_all_with_mode(
+        &mut self,
+        dir: &Path,
+        mode: impl Fn(&Path) -> Option<u32>,
+    ) -> io::Result<()> {
+        let mut missing: Vec<&Path> = dir.ancestors().take_while(|d| !d.exists()).collect();
+        missing.reverse();
+        for dir in missing {
+            fs::create_dir(dir)?;
+            self.undo.push(Undo::RemoveDir(dir.to_path_buf()));
+            if let Some(mode) = mode(dir) {
+                fs::set_permissions(dir, fs::Permissions::from_mode(mode))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Note that a file is about to be created at `path`.
+    pub fn create(&mut self, path: &Path) {
+        self.undo.push(Undo::Remove(path.to_path_buf()));
+    }
+
+    /// Move a file (or symlink) to another path.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)?;
+        self.undo.push(Undo::Rename {
+            from: to.to_path_buf(),
+            to: from.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Move whatever is at `path` out of the way, to be deleted once the
+    /// transaction is committed (or put back if it is rolled back).
+    pub fn set_aside(&mut self, path: &Path) -> io::Result<()> {
+        let name = path
+            .file_name()
+            .expect("failed file_name()")
+            .to_string_lossy();
+        let base = path.with_file_name(format!(".{name}.hushcrumbs-aside"));
+        // (never overwrite what an earlier, interrupted run left behind)
+        let mut aside = base.clone();
+        let mut n = 1;
+        while fs::symlink_metadata(&aside).is_ok() {
+            aside = PathBuf::from(format!("{}-{n}", base.display()));
+            n += 1;
+        }
+        self.rename(path, &aside)?;
+        self.set_aside.push(aside);
+        Ok(())
+    }
+
+    /// Replace the contents of a file.
+    pub fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let old = fs::read(path)?;
+        atomic_write(path, contents)?;
+        self.undo.push(Undo::Write {
+            path: path.to_path_buf(),
+            contents: old,
+        });
+        Ok(())
+    }
+
+    /// Take back every change, newest first.
+    pub fn rollback(self) -> io::Result<()> {
+        undo_all(self.undo.into_iter())
+    }
+
+    /// Keep the changes, and delete the files that were set aside.
+    /// Returns how to take back the changes later (with the contents of
+    /// the deleted files).
+    pub fn commit(self) -> io::Result<Vec<Undo>> {
+        let mut undo = Vec::new();
+        for change in self.undo {
+            match change {
+                Undo::Rename { from, to } if self.set_aside.contains(&from) => {
+                    let removal = Undo::removal(&from)?;
+                    fs::remove_file(&from)?;
+                    undo.push(match removal {
+                        Undo::Relink { target, .. } => Undo::Relink { path: to, target },
+                        Undo::Recreate { contents, mode, .. } => Undo::Recreate {
+                            path: to,
+                            contents,
+                            mode,
+                        },
+                        removal => removal,
+                    });
+                }
+                change => undo.push(change),
+            }
+        }
+        Ok(undo)
+    }
+}
@@ -0,0 +1,151 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_audit_log() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("echo TOKEN=hunter2 > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context
+        .run("set test .env API swordfish")
+        .assert()
+        .success();
+    context.run("ls test").assert().success();
+    context.run("rm test missing.txt").assert().failure();
+
+    // Who did what to which entry, but never the secrets:
+    let log = context.shell("cat t/.audit.ron").assert().success();
+    let log = log
+        .stdout(contains("operation:\"init\""))
+        .stdout(contains("operation:\"add\""))
+        .stdout(contains("keys:[\"API\"]"))
+        .stdout(contains("/.env\""))
+        .stdout(contains("operation:\"rm\""))
+        .stdout(contains("status:\"failed\""))
+        .stdout(contains("operation:\"ls\"").not())
+        .stdout(contains("hunter2").not())
+        .stdout(contains("swordfish").not());
+    let lines = String::from_utf8(log.get_output().stdout.clone()).unwrap();
+    assert_eq!(lines.lines().count(), 4);
+
+    context
+        .run("audit-log verify test")
+        .assert()
+        .success()
+        .stderr(contains("intact: 4 record(s)"));
+}
+
+#[test]
+fn test_audit_log_tampering() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.run("set test .env B 2").assert().success();
+    context.run("unset test .env A").assert().success();
+    context
+        .shell("cp t/.audit.ron audit.bak")
+        .assert()
+        .success();
+
+    // An edited record:
+    context
+        .shell("sed -i '2s/add/tag/' t/.audit.ron")
+        .assert()
+        .success();
+    context
+        .run("audit-log verify test")
+        .assert()
+        .failure()
+        .stderr(contains("line 2: record 2 was edited"));
+
+    // A deleted record:
+    context
+        .shell("cp audit.bak t/.audit.ron && sed -i 3d t/.audit.ron")
+        .assert()
+        .success();
+    context
+        .run("audit-log verify test")
+        .assert()
+        .failure()
+        .stderr(contains("record 3 is missing"));
+
+    // A deleted last record, caught with the last hash seen before:
+    context
+        .shell("cp audit.bak t/.audit.ron && sed -i '$d' t/.audit.ron")
+        .assert()
+        .success();
+    context.run("audit-log verify test").assert().success();
+    let hash = context
+        .shell("tail -1 audit.bak | sed 's/.*hash:\"\\(.*\\)\")$/\\1/'")
+        .assert()
+        .success();
+    let hash = String::from_utf8(hash.get_output().stdout.clone()).unwrap();
+    context
+        .run(&format!(
+            "audit-log verify test --last-hash {}",
+            hash.trim()
+        ))
+        .assert()
+        .failure()
+        .stderr(contains("is missing: the log was truncated"));
+}
+
+#[test]
+fn test_audit_log_of_sync() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.run("init other o").assert().success();
+    context
+        .shell("echo A=1 > a.env && echo B=1 > b.env && echo C=1 > .env")
+        .assert()
+        .success();
+    context
+        .shell(r#"printf '%s' '(files: {".env": "test"})' > .hushcrumbs.ron"#)
+        .assert()
+        .success();
+    context.run("sync").assert().success();
+    context.run("add test a.env").assert().success();
+    context.run("add other b.env").assert().success();
+    context.run("mode test a.env copy").assert().success();
+    context.run("mode other b.env copy").assert().success();
+
+    // Without a backup name, each backup that was changed is audited:
+    context
+        .shell("echo A=2 > a.env && echo B=2 > b.env")
+        .assert()
+        .success();
+    context.run("sync-copies").assert().success();
+    context
+        .shell("cat t/.audit.ron")
+        .assert()
+        .success()
+        .stdout(contains("operation:\"sync\""))
+        .stdout(contains("/.env\""))
+        .stdout(contains("operation:\"sync-copies\""))
+        .stdout(contains("/a.env\""))
+        .stdout(contains("/b.env\"").not());
+    context
+        .shell("cat o/.audit.ron")
+        .assert()
+        .success()
+        .stdout(contains("operation:\"sync-copies\""))
+        .stdout(contains("/b.env\""))
+        .stdout(contains("operation:\"sync\"").not());
+
+    // Nothing is recorded when nothing was changed:
+    context.run("sync-copies").assert().success();
+    context.run("sync").assert().success();
+    context
+        .shell("grep -c sync t/.audit.ron")
+        .assert()
+        .success()
+        .stdout("2\n");
+    context.run("audit-log verify test").assert().success();
+    context.run("audit-log verify other").assert().success();
+}
@@ -0,0 +1,118 @@
+mod common;
+use common::*;
+
+const SCHEMA: &str = r#"(keys: {
+    "DB_HOST": (required: true),
+    "DB_PORT": (required: true, type: Integer),
+    "MODE": (allowed: ["dev", "prod"]),
+    "API_KEY": (pattern: "^[a-f0-9]{8}$"),
+})"#;
+
+#[test]
+fn test_check_local_schema() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(&format!("printf '%s' '{SCHEMA}' > .env.schema"))
+        .assert()
+        .success();
+    // A file that is missing a required key can't be added:
+    context
+        .shell("printf 'DB_HOST=localhost\\n' > .env")
+        .assert()
+        .success();
+    context
+        .run("add test .env")
+        .assert()
+        .failure()
+        .stderr(contains("DB_PORT: required key is missing"));
+    assert_regular_file_exists(&format!("{}/.env", context.temp_dir_path));
+
+    context
+        .shell("printf 'DB_HOST=localhost\\nDB_PORT=5432\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context.run("check test").assert().success();
+    context.run("check").assert().success();
+
+    // Invalid writes are rejected:
+    context
+        .run("set test .env DB_PORT five")
+        .assert()
+        .failure()
+        .stderr(contains("DB_PORT: expected a value of type Integer"));
+    context
+        .run("set test .env MODE staging")
+        .assert()
+        .failure()
+        .stderr(contains("MODE: must be one of"));
+    context
+        .run("set test .env API_KEY nope")
+        .assert()
+        .failure()
+        .stderr(contains("API_KEY: does not match pattern"));
+    context
+        .run("unset test .env DB_HOST")
+        .assert()
+        .failure()
+        .stderr(contains("DB_HOST: required key is missing"));
+    context.run("set test .env MODE prod").assert().success();
+    context
+        .run("get test .env DB_PORT")
+        .assert()
+        .stdout("5432\n");
+
+    // Invalid edits are rejected too:
+    context
+        .run("edit test .env")
+        .env("EDITOR", "sed -i s/5432/x/")
+        .assert()
+        .failure();
+    context
+        .run("get test .env DB_PORT")
+        .assert()
+        .stdout("5432\n");
+}
+
+#[test]
+fn test_check_manifest_schema() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'DB_HOST=localhost\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context
+        .shell(&format!("printf '%s' '{SCHEMA}' > schema.ron"))
+        .assert()
+        .success();
+    // The file doesn't match the schema yet:
+    context
+        .run("schema test .env schema.ron")
+        .assert()
+        .failure()
+        .stderr(contains("DB_PORT: required key is missing"));
+    context.run("set test .env DB_PORT 5432").assert().success();
+    context
+        .run("schema test .env schema.ron")
+        .assert()
+        .success();
+    context.run("check test").assert().success();
+
+    // Change the file behind the schema's back:
+    context
+        .shell("printf 'DB_PORT=nope\\n' > .env")
+        .assert()
+        .success();
+    context
+        .run("check test")
+        .assert()
+        .failure()
+        .stderr(contains("DB_HOST: required key is missing"))
+        .stderr(contains("DB_PORT: expected a value of type Integer"));
+
+    context.run("schema test .env --clear").assert().success();
+    context.run("check test").assert().success();
+}
@@ -0,0 +1,80 @@
+mod common;
+use common::*;
+
+/// Write to the backup file directly, as a `git pull` of the backup would.
+const WRITE_BACKUP: &str = "cat > $(ls t/* | grep -v paths.ron)";
+
+#[test]
+fn test_sync_copies() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.run("mode test .env copy").assert().success();
+
+    // Changes to the copy are pulled into the backup:
+    context.shell("echo A=2 > .env").assert().success();
+    context
+        .run("sync-copies test")
+        .assert()
+        .success()
+        .stderr(contains("Pulled changes from the copy"));
+    context
+        .run("get test .env A")
+        .assert()
+        .success()
+        .stdout(contains("2"));
+
+    // Changes to the backup are pushed out to the copy:
+    context
+        .shell(&format!("echo A=3 | {WRITE_BACKUP}"))
+        .assert()
+        .success();
+    context
+        .run("sync-copies")
+        .assert()
+        .success()
+        .stderr(contains("Pushed changes from the backup"));
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=3"));
+    context
+        .run("sync-copies test")
+        .assert()
+        .success()
+        .stderr(contains("All copies are in sync"));
+}
+
+#[test]
+fn test_sync_copies_conflict() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.shell("rm .env").assert().success();
+    context.run("restore test --copy").assert().success();
+    context.shell("echo A=local > .env").assert().success();
+    context
+        .shell(&format!("echo A=remote | {WRITE_BACKUP}"))
+        .assert()
+        .success();
+    context
+        .run("sync-copies test")
+        .assert()
+        .failure()
+        .stderr(contains("both the copy and the backup"))
+        .stderr(contains("-A=remote\n+A=local"));
+    // Neither side was touched:
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=local"));
+    context
+        .run("get test .env A")
+        .assert()
+        .success()
+        .stdout(contains("remote"));
+}
@@ -0,0 +1,111 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_dry_run_init_and_add() {
+    let context = TestBed::new();
+    context
+        .run("init test t --dry-run")
+        .assert()
+        .success()
+        .stdout(contains("Would create the directory t"))
+        .stdout(contains("Would write config.ron"));
+    context.shell("ls t").assert().failure();
+    context.shell("ls config.ron").assert().failure();
+
+    context.run("init test t").assert().success();
+    context.shell("echo hi > hi.txt").assert().success();
+    context
+        .run("add test hi.txt --dry-run")
+        .assert()
+        .success()
+        .stdout(contains("Would copy hi.txt to"))
+        .stdout(contains("Would create a symlink at"))
+        .stdout(contains("paths.ron:\n  +(\n  +    files: {"));
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+    assert_regular_file_exists(hi);
+    context.shell("ls t").assert().success().stdout("");
+}
+
+#[test]
+fn test_dry_run_remove_and_restore() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hi > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+
+    // (no confirmation is needed, as nothing is deleted)
+    context
+        .run("rm test hi.txt --delete --dry-run")
+        .assert()
+        .success()
+        .stdout(contains(format!("Would remove {hi}")))
+        .stdout(contains("-        \"").and(contains("+    files: {},")));
+    assert_path_is_symlink(hi);
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("hi.txt"));
+
+    context.shell("rm hi.txt").assert().success();
+    context
+        .run("--dry-run restore test")
+        .assert()
+        .success()
+        .stdout(contains("create a symlink"));
+    assert_path_not_exists(hi);
+}
+
+#[test]
+fn test_dry_run_changes_to_a_backup() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    let env = &format!("{}/.env", context.temp_dir_path);
+
+    context
+        .run("set test .env A 2 --dry-run")
+        .assert()
+        .success()
+        .stdout(contains(format!("(the backup of {env})")));
+    context
+        .run("mode test .env copy --dry-run")
+        .assert()
+        .success()
+        .stdout(contains("Would place"))
+        .stdout(contains(format!("at {env} as a copy")))
+        .stdout(contains("+            mode: Copy,"));
+    context
+        .run("--dry-run undo test")
+        .assert()
+        .success()
+        .stdout(contains("Would undo `add .env`"));
+    assert_path_is_symlink(env);
+    context
+        .run("get test .env A")
+        .assert()
+        .success()
+        .stdout("1\n");
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("undo").not());
+}
+
+#[test]
+fn test_dry_run_unsupported() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context
+        .run("--dry-run run test -- true")
+        .assert()
+        .failure()
+        .stderr(contains("The run command does not support --dry-run"));
+}
@@ -0,0 +1,62 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_edit() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hello > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context
+        .run("edit test hi.txt")
+        .env("EDITOR", "sed -i s/hello/goodbye/")
+        .assert()
+        .success();
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+    // The symlink is untouched, and the backup has the new contents:
+    assert_path_is_symlink(hi);
+    context
+        .shell("cat hi.txt")
+        .assert()
+        .success()
+        .stdout("goodbye\n");
+}
+
+#[test]
+fn test_edit_unchanged() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hello > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context
+        .run("edit test hi.txt")
+        .env("EDITOR", "true")
+        .assert()
+        .failure()
+        .stderr(contains("File unchanged"));
+    // A failing editor never modifies the backup:
+    context
+        .run("edit test hi.txt")
+        .env("EDITOR", "sed -i s/hello/goodbye/ \"$1\"; false")
+        .assert()
+        .failure()
+        .stderr(contains("Editor exited unsuccessfully"));
+    context
+        .shell("cat hi.txt")
+        .assert()
+        .success()
+        .stdout("hello\n");
+}
+
+#[test]
+fn test_edit_not_in_backup() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hello > hi.txt").assert().success();
+    context
+        .run("edit test hi.txt")
+        .env("EDITOR", "true")
+        .assert()
+        .failure()
+        .stderr(contains("File not found in backup"));
+}
@@ -0,0 +1,58 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_env_export() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(r#"printf 'A=one\nB="it'"'"'s two"\n' > .env"#)
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context
+        .run("env test .env --shell posix")
+        .assert()
+        .success()
+        .stdout("export A='one'\nexport B='it'\\''s two'\n");
+    context
+        .run("env test .env --shell fish")
+        .assert()
+        .success()
+        .stdout("set -gx A 'one'\nset -gx B 'it\\'s two'\n");
+    context
+        .run("env test .env --shell bash --unset")
+        .assert()
+        .success()
+        .stdout("unset A\nunset B\n");
+    context
+        .run("env test .env --shell fish --unset")
+        .assert()
+        .success()
+        .stdout("set -e A\nset -e B\n");
+    context
+        .run("env test .env --shell powershell")
+        .assert()
+        .success()
+        .stdout("$env:A = 'one'\n$env:B = 'it''s two'\n");
+}
+
+#[test]
+fn test_env_eval() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(r#"printf 'SECRET="a $b \\"c\\" `d`"\n' > .env"#)
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    let env = format!(
+        "{} -c config.ron env test .env --shell posix",
+        assert_cmd::cargo::cargo_bin(env!("CARGO_PKG_NAME")).display()
+    );
+    context
+        .shell(&format!(r#"eval "$({env})" && printf '%s' "$SECRET""#))
+        .assert()
+        .success()
+        .stdout("a $b \"c\" `d`");
+}
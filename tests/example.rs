@@ -0,0 +1,67 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_example() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf '# Database\\nDB_HOST=db.example.com # hostname\\nDB_PORT=5432\\nexport DB_PASS=\"s3cret\"\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context.run("example test .env").assert().success();
+    assert_regular_file_exists(&format!("{}/.env.example", context.temp_dir_path));
+    context
+        .shell("cat .env.example")
+        .assert()
+        .success()
+        .stdout("# Database\nDB_HOST= # hostname\nDB_PORT=\nexport DB_PASS=\"\"\n");
+    context.run("example test .env --check").assert().success();
+
+    context
+        .run("example test .env -o env.hints --hints")
+        .assert()
+        .success();
+    context.shell("cat env.hints").assert().success().stdout(
+        "# Database\nDB_HOST=\"<string>\" # hostname\nDB_PORT=\"<number>\"\nexport DB_PASS=\"<string>\"\n",
+    );
+    // Only finite numbers are hinted as numbers:
+    context
+        .shell("printf 'A=-1.5e3\\nB=nan\\nC=inf\\nD=Infinity\\nE=1e999\\n' > .env")
+        .assert()
+        .success();
+    context
+        .run("example test .env -o env.hints --hints")
+        .assert()
+        .success();
+    context
+        .shell("cat env.hints")
+        .assert()
+        .success()
+        .stdout("A=\"<number>\"\nB=\"<string>\"\nC=\"<string>\"\nD=\"<string>\"\nE=\"<string>\"\n");
+}
+
+#[test]
+fn test_example_check_drift() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'A=1\\nB=2\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    // There is no example yet:
+    context.run("example test .env --check").assert().failure();
+    context.run("example test .env").assert().success();
+    context.run("set test .env C 3").assert().success();
+    context.run("unset test .env A").assert().success();
+    context
+        .run("example test .env --check")
+        .assert()
+        .failure()
+        .stderr(contains("missing keys: [\"C\"]"))
+        .stderr(contains("extra keys: [\"A\"]"));
+    context.run("example test .env").assert().success();
+    context.run("example test .env --check").assert().success();
+}
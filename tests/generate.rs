@@ -0,0 +1,100 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_generate_new_file() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    // The file is created and added to the backup:
+    context
+        .run("generate test .env SECRET_KEY --length 40 --charset hex")
+        .assert()
+        .success();
+    assert_path_is_symlink(&format!("{}/.env", context.temp_dir_path));
+    context
+        .shell("grep -E '^SECRET_KEY=[0-9a-f]{40}$' .env")
+        .assert()
+        .success();
+    context
+        .shell("test \"$(stat -L -c %a .env)\" = 600")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_generate_if_missing() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf '# Keys\\nEXISTING=keep\\n' > .env")
+        .assert()
+        .success();
+    context
+        .run("generate test .env EXISTING --if-missing")
+        .assert()
+        .success();
+    context
+        .run("generate test .env TOKEN --charset base64 --length 20 --if-missing")
+        .assert()
+        .success();
+    context
+        .run("generate test .env WORDS --charset words --length 8")
+        .assert()
+        .success();
+    context
+        .run("get test .env EXISTING")
+        .assert()
+        .success()
+        .stdout("keep\n");
+    context
+        .shell("grep -E '^TOKEN=[A-Za-z0-9_-]{20}$' .env")
+        .assert()
+        .success();
+    context
+        .shell("grep -E '^WORDS=[a-z]+(-[a-z]+){7}$' .env")
+        .assert()
+        .success();
+    // Running it again does not change the existing value:
+    let token = context.run("get test .env TOKEN").output().unwrap().stdout;
+    context
+        .run("generate test .env TOKEN --charset base64 --length 20 --if-missing")
+        .assert()
+        .success();
+    context
+        .run("get test .env TOKEN")
+        .assert()
+        .success()
+        .stdout(String::from_utf8(token).unwrap());
+    // Without --if-missing, the value is replaced:
+    context
+        .run("generate test .env EXISTING")
+        .assert()
+        .success();
+    context
+        .run("get test .env EXISTING")
+        .assert()
+        .success()
+        .stdout(contains("keep").not());
+}
+
+#[test]
+fn test_generate_too_short() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .run("generate test .env KEY --length 0")
+        .assert()
+        .failure()
+        .stderr(contains("too weak"));
+    context
+        .run("generate test .env KEY --length 4 --charset words")
+        .assert()
+        .failure()
+        .stderr(contains("the minimum is 8"));
+    assert_path_not_exists(&format!("{}/.env", context.temp_dir_path));
+    context
+        .run("generate test .env KEY --length 8 --charset words")
+        .assert()
+        .success();
+}
@@ -0,0 +1,190 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_undo() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context
+        .run("edit test .env")
+        .env("EDITOR", "sed -i s/1/2/")
+        .assert()
+        .success();
+    context
+        .run("rm test .env --delete --no-confirm")
+        .assert()
+        .success();
+    let env = &format!("{}/.env", context.temp_dir_path);
+    assert_path_not_exists(env);
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("add .env"))
+        .stdout(contains("edit .env"))
+        .stdout(contains("rm .env"));
+
+    // Even a deleted file comes back:
+    context.run("undo test").assert().success();
+    assert_path_is_symlink(env);
+    context.shell("cat .env").assert().success().stdout("A=2\n");
+
+    context.run("undo test 2").assert().success();
+    assert_regular_file_exists(env);
+    context.shell("cat .env").assert().success().stdout("A=1\n");
+    context.shell("ls t").assert().success().stdout("");
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("undo add .env"))
+        .stdout(contains("(undone)"));
+
+    context
+        .run("undo test")
+        .assert()
+        .failure()
+        .stderr(contains("Only 0 operation(s)"));
+}
+
+#[test]
+fn test_undo_restore() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hi > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context
+        .shell("rm hi.txt && echo local > hi.txt")
+        .assert()
+        .success();
+    context.run("restore test --no-confirm").assert().success();
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+    assert_path_is_symlink(hi);
+
+    // The file that was overwritten is put back:
+    context.run("undo test").assert().success();
+    assert_regular_file_exists(hi);
+    context
+        .shell("cat hi.txt")
+        .assert()
+        .success()
+        .stdout("local\n");
+}
+
+#[test]
+fn test_undo_after_unrecorded_change() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hi > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context.shell("echo >> t/paths.ron").assert().success();
+    context
+        .run("undo test")
+        .assert()
+        .failure()
+        .stderr(contains("cannot be undone"));
+}
+
+#[test]
+fn test_undo_edit_of_placed_copies() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hello > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context.run("link test hi.txt copy.txt").assert().success();
+    context.run("mode test copy.txt copy").assert().success();
+    context.run("link test hi.txt hard.txt").assert().success();
+    context
+        .run("mode test hard.txt hardlink")
+        .assert()
+        .success();
+    context
+        .run("edit test hi.txt")
+        .env("EDITOR", "sed -i s/hello/goodbye/")
+        .assert()
+        .success();
+    context
+        .shell("cat hi.txt copy.txt hard.txt")
+        .assert()
+        .success()
+        .stdout("goodbye\ngoodbye\ngoodbye\n");
+
+    // The copy and the hardlink go back with the backup file:
+    context.run("undo test").assert().success();
+    context
+        .shell("cat hi.txt copy.txt hard.txt")
+        .assert()
+        .success()
+        .stdout("hello\nhello\nhello\n");
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("copy.txt").and(contains("copy (diverged)").not()))
+        .stdout(contains("stale hardlink").not());
+}
+
+#[test]
+fn test_undo_every_change() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.run("set test .env B 2").assert().success();
+    context.run("tag test prod .env").assert().success();
+    context.run("mode test .env copy").assert().success();
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("set .env B"))
+        .stdout(contains("tag prod"))
+        .stdout(contains("mode .env copy"));
+
+    context.run("undo test 3").assert().success();
+    let env = &format!("{}/.env", context.temp_dir_path);
+    assert_path_is_symlink(env);
+    context.shell("cat .env").assert().success().stdout("A=1\n");
+    context
+        .run("ls test --tag prod")
+        .assert()
+        .failure()
+        .stderr(contains("No files in the backup match"));
+}
+
+#[test]
+fn test_prune_history() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.run("set test .env A 2").assert().success();
+    context.run("set test .env A 3").assert().success();
+
+    context
+        .run("history test --keep 1")
+        .assert()
+        .success()
+        .stderr(contains("2 operation(s) forgotten"));
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("add .env").not());
+    context.run("undo test").assert().success();
+    context
+        .run("undo test")
+        .assert()
+        .failure()
+        .stderr(contains("Only 0 operation(s)"));
+
+    context.run("history test --clear").assert().success();
+    context
+        .run("history test")
+        .assert()
+        .success()
+        .stdout(contains("set .env").not());
+}
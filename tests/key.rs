@@ -0,0 +1,249 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_dotenv_get_set_unset() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf '# Database\\nDB_USER=admin # the user\\nexport DB_PASS=\"s3cret\"\\n\\nAPI_KEY='\"'\"'abc def'\"'\"'\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+
+    context
+        .run("get test .env DB_USER")
+        .assert()
+        .success()
+        .stdout("admin\n");
+    context
+        .run("get test .env API_KEY")
+        .assert()
+        .success()
+        .stdout("abc def\n");
+    context
+        .run("get test .env MISSING")
+        .assert()
+        .failure()
+        .stderr(contains("not found"));
+
+    // Setting keys keeps the comments, ordering and quoting:
+    context
+        .run("set test .env DB_PASS hunter2")
+        .assert()
+        .success();
+    context
+        .run("set test .env API_KEY 'it works'")
+        .assert()
+        .success();
+    context
+        .run("set test .env NEW_KEY 'with spaces'")
+        .assert()
+        .success();
+    context.run("unset test .env DB_USER").assert().success();
+    context.run("unset test .env DB_USER").assert().failure();
+    let env = &format!("{}/.env", context.temp_dir_path);
+    assert_path_is_symlink(env);
+    context.shell("cat .env").assert().success().stdout(
+        "# Database\nexport DB_PASS=\"hunter2\"\n\nAPI_KEY='it works'\nNEW_KEY=\"with spaces\"\n",
+    );
+}
+
+#[test]
+fn test_dotenv_set_stdin() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("touch .env").assert().success();
+    context.run("add test .env").assert().success();
+    context
+        .run("set test .env TOKEN --stdin")
+        .write_stdin("from-stdin\n")
+        .assert()
+        .success();
+    context
+        .run("get test .env TOKEN")
+        .assert()
+        .success()
+        .stdout("from-stdin\n");
+    // Keys must be valid variable names:
+    context
+        .run("set test .env 'BAD KEY' value")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid key name"));
+}
+
+#[test]
+fn test_dotenv_not_in_backup() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context
+        .run("get test .env A")
+        .assert()
+        .failure()
+        .stderr(contains("File not found in backup"));
+}
+
+#[test]
+fn test_json_get_set_unset() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(r#"printf '{\n  "type": "service_account",\n  "port": 80,\n  "hosts": ["a", "b"]\n}\n' > credentials.json"#)
+        .assert()
+        .success();
+    context.run("add test credentials.json").assert().success();
+    context
+        .run("get test credentials.json type")
+        .assert()
+        .success()
+        .stdout("service_account\n");
+    context
+        .run("get test credentials.json /hosts/1")
+        .assert()
+        .success()
+        .stdout("b\n");
+    context
+        .run("set test credentials.json port 8080")
+        .assert()
+        .success();
+    context
+        .run("set test credentials.json auth.token abc")
+        .assert()
+        .success();
+    context
+        .run("unset test credentials.json hosts.0")
+        .assert()
+        .success();
+    context.shell("cat credentials.json").assert().success().stdout(
+        "{\n  \"type\": \"service_account\",\n  \"port\": 8080,\n  \"hosts\": [\n    \"b\"\n  ],\n  \"auth\": {\n    \"token\": \"abc\"\n  }\n}\n",
+    );
+}
+
+#[test]
+fn test_json_formatting() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(r#"printf '{\n    "zeta": 1,\n    "alpha": {"b": true, "a": 2}\n}' > settings.json"#)
+        .assert()
+        .success();
+    context.run("add test settings.json").assert().success();
+    context
+        .run("set test settings.json alpha.b false")
+        .assert()
+        .success();
+    // The order of the keys and the indentation are kept:
+    context
+        .shell("cat settings.json")
+        .assert()
+        .success()
+        .stdout(
+        "{\n    \"zeta\": 1,\n    \"alpha\": {\n        \"b\": false,\n        \"a\": 2\n    }\n}",
+    );
+}
+
+#[test]
+fn test_toml_get_set_unset() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(r#"printf '# Settings\n[database]\nuser = "admin" # the user\nport = 5432\n' > config.toml"#)
+        .assert()
+        .success();
+    context.run("add test config.toml").assert().success();
+    context
+        .run("get test config.toml database.user")
+        .assert()
+        .success()
+        .stdout("admin\n");
+    context
+        .run("set test config.toml database.user root")
+        .assert()
+        .success();
+    context
+        .run("set test config.toml database.port 6543")
+        .assert()
+        .success();
+    context
+        .run("set test config.toml server.password hunter2")
+        .assert()
+        .success();
+    context
+        .run("unset test config.toml database.missing")
+        .assert()
+        .failure()
+        .stderr(contains("not found"));
+    // Formatting and comments are preserved:
+    context.shell("cat config.toml").assert().success().stdout(
+        "# Settings\n[database]\nuser = \"root\" # the user\nport = 6543\n\n[server]\npassword = \"hunter2\"\n",
+    );
+}
+
+#[test]
+fn test_yaml_forced_format() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'db:\\n  password: old\\n' > secrets")
+        .assert()
+        .success();
+    context.run("add test secrets").assert().success();
+    context
+        .run("set test secrets db.password new --format yaml")
+        .assert()
+        .success();
+    context
+        .run("get test secrets db.password --format yaml")
+        .assert()
+        .success()
+        .stdout("new\n");
+    // Without --format, the file is treated as dotenv:
+    context
+        .run("get test secrets db.password")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_yaml_with_comments_is_not_rewritten() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf '# Production\\ndb:\\n  password: old # rotate me\\n' > secrets.yaml")
+        .assert()
+        .success();
+    context.run("add test secrets.yaml").assert().success();
+    context
+        .run("get test secrets.yaml db.password")
+        .assert()
+        .success()
+        .stdout("old\n");
+    context
+        .run("set test secrets.yaml db.password new")
+        .assert()
+        .failure()
+        .stderr(contains("has comments, which would be lost"));
+    context
+        .shell("cat secrets.yaml")
+        .assert()
+        .success()
+        .stdout("# Production\ndb:\n  password: old # rotate me\n");
+
+    // A '#' inside a value is not a comment:
+    context
+        .shell("printf 'db:\\n  password: \"a #b\"\\n  url: x#y\\n' > other.yaml")
+        .assert()
+        .success();
+    context.run("add test other.yaml").assert().success();
+    context
+        .run("set test other.yaml db.password new")
+        .assert()
+        .success();
+    context
+        .shell("cat other.yaml")
+        .assert()
+        .success()
+        .stdout("db:\n  password: new\n  url: x#y\n");
+}
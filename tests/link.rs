@@ -0,0 +1,123 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_link() {
+    let mut context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("mkdir main worktree && echo A=1 > main/.env")
+        .assert()
+        .success();
+    context.run("add test main/.env").assert().success();
+    context
+        .run("link test main/.env worktree/.env")
+        .assert()
+        .success();
+    let main = &format!("{}/main/.env", context.temp_dir_path);
+    let worktree = &format!("{}/worktree/.env", context.temp_dir_path);
+    assert_path_is_symlink(worktree);
+    assert_eq!(canonicalize(main).unwrap(), canonicalize(worktree).unwrap());
+    // The new path must not already exist:
+    context
+        .run("link test main/.env worktree/.env")
+        .assert()
+        .failure();
+
+    // Both paths are listed:
+    assert_command_output_equals_json(
+        &mut context.binary,
+        "ls test --json",
+        serde_json::json!({
+            "backup_name": "test",
+            "files": [main, worktree],
+            "aliases": {worktree: main},
+        }),
+    );
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("(alias of"));
+
+    // Edits through either path reach both:
+    context.run("set test worktree/.env A 2").assert().success();
+    context.shell("cat main/.env").assert().stdout("A=2\n");
+
+    // Unlink drops just one path:
+    context.run("unlink test worktree/.env").assert().success();
+    assert_path_not_exists(worktree);
+    assert_path_is_symlink(main);
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("alias").not());
+    // But not the last one:
+    context.run("unlink test main/.env").assert().failure();
+}
+
+#[test]
+fn test_link_restore_and_rm() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("mkdir main worktree && echo A=1 > main/.env")
+        .assert()
+        .success();
+    context.run("add test main/.env").assert().success();
+    context
+        .run("link test main/.env worktree/.env")
+        .assert()
+        .success();
+    let main = &format!("{}/main/.env", context.temp_dir_path);
+    let worktree = &format!("{}/worktree/.env", context.temp_dir_path);
+    let backup = canonicalize(main).unwrap();
+
+    // Restore recreates the alias symlink:
+    context.shell("rm worktree/.env").assert().success();
+    context.run("restore test").assert().success();
+    assert_path_is_symlink(worktree);
+
+    // Removing the original keeps the backup for the alias, which takes over:
+    context.run("rm test main/.env").assert().success();
+    assert_regular_file_exists(main);
+    assert_regular_file_exists(backup.to_str().unwrap());
+    context.shell("cat worktree/.env").assert().stdout("A=1\n");
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("alias").not());
+    context.run("rm test worktree/.env").assert().success();
+    assert_regular_file_exists(worktree);
+    assert_path_not_exists(backup.to_str().unwrap());
+}
+
+#[test]
+fn test_unlink_content_addressed() {
+    let context = TestBed::new();
+    context
+        .run("init test t --content-addressed")
+        .assert()
+        .success();
+    context
+        .shell("echo same > a.txt && echo same > c.txt")
+        .assert()
+        .success();
+    context.run("add test a.txt").assert().success();
+    context.run("add test c.txt").assert().success();
+    // c.txt shares the blob of a.txt, but it is not an alias of it:
+    context
+        .run("unlink test c.txt")
+        .assert()
+        .failure()
+        .stderr(contains("is the only path linked"));
+    assert_path_is_symlink(&format!("{}/c.txt", context.temp_dir_path));
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("c.txt"));
+}
@@ -0,0 +1,128 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_hardlink_mode() {
+    let mut context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.run("restore test --hardlink").assert().success();
+    // (a valid symlink is left alone)
+    let env = &format!("{}/.env", context.temp_dir_path);
+    assert_path_is_symlink(env);
+    context.run("mode test .env hardlink").assert().success();
+    assert_regular_file_exists(env);
+    assert_command_output_equals_json(
+        &mut context.binary,
+        "status test --json",
+        serde_json::json!({
+            "backup_name": "test",
+            "files": [{"path": env, "status": "hardlink"}],
+            "mixed_links": false,
+        }),
+    );
+
+    // Setting a key rewrites the backup file, and the hard link follows:
+    context.run("set test .env A 2").assert().success();
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=2"));
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stdout(contains("hardlinked"));
+
+    // Removing it leaves a regular file behind:
+    context.run("rm test .env").assert().success();
+    assert_regular_file_exists(env);
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=2"));
+    context
+        .shell("ls t")
+        .assert()
+        .success()
+        .stdout(predicate::eq("paths.ron\n"));
+}
+
+#[test]
+fn test_copy_mode() {
+    let mut context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add test .env").assert().success();
+    context.shell("rm .env").assert().success();
+    context.run("restore test --copy").assert().success();
+    let env = &format!("{}/.env", context.temp_dir_path);
+    assert_regular_file_exists(env);
+    assert_command_output_equals_json(
+        &mut context.binary,
+        "status test --json",
+        serde_json::json!({
+            "backup_name": "test",
+            "files": [{"path": env, "status": "copy"}],
+            "mixed_links": false,
+        }),
+    );
+
+    // An unchanged copy is kept up to date:
+    context.run("set test .env A 2").assert().success();
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=2"));
+
+    // But a copy with local changes is not overwritten:
+    context.shell("echo A=local > .env").assert().success();
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stdout(contains("copy (diverged)"));
+    context
+        .run("set test .env A 3")
+        .assert()
+        .success()
+        .stderr(contains("has local changes"));
+    context
+        .shell("cat .env")
+        .assert()
+        .success()
+        .stdout(contains("A=local"));
+
+    // Restoring with --symlink turns it back into a symlink:
+    context
+        .run("restore test --symlink --overwrite")
+        .assert()
+        .success();
+    assert_path_is_symlink(env);
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stdout(contains("linked"));
+}
+
+#[test]
+fn test_remove_copy_with_delete() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo hi > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    context.run("mode test hi.txt copy").assert().success();
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+    assert_regular_file_exists(hi);
+    context
+        .run("rm test hi.txt --delete --no-confirm")
+        .assert()
+        .success();
+    assert_path_not_exists(hi);
+}
@@ -0,0 +1,145 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_content_addressed() {
+    let context = TestBed::new();
+    context
+        .run("init test t --content-addressed")
+        .assert()
+        .success();
+    context
+        .shell("mkdir a b && echo secret > a/.env && echo secret > b/.env && echo other > c.txt")
+        .assert()
+        .success();
+    context.run("add test a/.env").assert().success();
+    context.run("add test b/.env").assert().success();
+    context.run("add test c.txt").assert().success();
+    let a = &format!("{}/a/.env", context.temp_dir_path);
+    let b = &format!("{}/b/.env", context.temp_dir_path);
+    let c = &format!("{}/c.txt", context.temp_dir_path);
+    assert_path_is_symlink(a);
+    assert_path_is_symlink(b);
+    // Identical files share one blob:
+    let blob = canonicalize(a).unwrap();
+    assert_eq!(blob, canonicalize(b).unwrap());
+    assert_ne!(blob, canonicalize(c).unwrap());
+
+    // An edit moves the path to a new blob, and leaves the other alone:
+    context.run("set test a/.env KEY value").assert().success();
+    assert_path_is_symlink(a);
+    assert_ne!(blob, canonicalize(a).unwrap());
+    context.run("get test b/.env KEY").assert().failure();
+    context
+        .shell("cat b/.env")
+        .assert()
+        .success()
+        .stdout("secret\n");
+
+    // The blob is only deleted when nothing refers to it:
+    let new_blob = canonicalize(a).unwrap();
+    context.run("rm test a/.env").assert().success();
+    assert_regular_file_exists(a);
+    assert_path_not_exists(new_blob.to_str().unwrap());
+    assert_regular_file_exists(blob.to_str().unwrap());
+    assert_path_is_symlink(b);
+    context
+        .run("rm test b/.env --delete --no-confirm")
+        .assert()
+        .success();
+    assert_path_not_exists(blob.to_str().unwrap());
+}
+
+#[test]
+fn test_content_addressed_aliases_follow_edits() {
+    let context = TestBed::new();
+    context
+        .run("init test t --content-addressed")
+        .assert()
+        .success();
+    context
+        .shell("mkdir main worktree other && echo A=1 > main/.env && echo A=1 > other/.env")
+        .assert()
+        .success();
+    context.run("add test main/.env").assert().success();
+    context.run("add test other/.env").assert().success();
+    context
+        .run("link test main/.env worktree/.env")
+        .assert()
+        .success();
+    let old_blob = canonicalize(format!("{}/main/.env", context.temp_dir_path)).unwrap();
+
+    context.run("set test worktree/.env A 2").assert().success();
+    context
+        .shell("cat main/.env worktree/.env other/.env")
+        .assert()
+        .success()
+        .stdout("A=2\nA=2\nA=1\n");
+    let main = canonicalize(format!("{}/main/.env", context.temp_dir_path)).unwrap();
+    let worktree = canonicalize(format!("{}/worktree/.env", context.temp_dir_path)).unwrap();
+    assert_eq!(main, worktree);
+    assert_ne!(main, old_blob);
+    assert_regular_file_exists(old_blob.to_str().unwrap());
+    context.run("ls test").assert().success();
+}
+
+#[test]
+fn test_content_addressed_copies_of_other_entries() {
+    let context = TestBed::new();
+    context
+        .run("init test t --content-addressed")
+        .assert()
+        .success();
+    context
+        .shell("echo A=1 > a.env && echo A=2 > c.env")
+        .assert()
+        .success();
+    context.run("add test a.env").assert().success();
+    context.run("add test c.env").assert().success();
+    context.run("mode test c.env copy").assert().success();
+    context.shell("echo A=1 > c.env").assert().success();
+
+    // a.env moves to the blob of c.env, but c.env is no alias of it, so its
+    // local changes are kept:
+    context.run("set test a.env A 2").assert().success();
+    context
+        .shell("cat a.env c.env")
+        .assert()
+        .success()
+        .stdout("A=2\nA=1\n");
+}
+
+#[test]
+fn test_migrate() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("mkdir a b && echo secret > a/.env && echo secret > b/.env && echo other > c.txt")
+        .assert()
+        .success();
+    context.run("add test a/.env").assert().success();
+    context.run("add test b/.env").assert().success();
+    context.run("add test c.txt").assert().success();
+    let a = &format!("{}/a/.env", context.temp_dir_path);
+    let b = &format!("{}/b/.env", context.temp_dir_path);
+    let old_a = canonicalize(a).unwrap();
+    assert_ne!(old_a, canonicalize(b).unwrap());
+
+    context
+        .run("migrate test")
+        .assert()
+        .success()
+        .stderr(contains("3 file(s) stored in 2 blob(s)"));
+    assert_path_is_symlink(a);
+    assert_path_is_symlink(b);
+    assert_eq!(canonicalize(a).unwrap(), canonicalize(b).unwrap());
+    assert_path_not_exists(old_a.to_str().unwrap());
+    context
+        .shell("cat a/.env b/.env c.txt")
+        .assert()
+        .success()
+        .stdout("secret\nsecret\nother\n");
+    context.run("ls test").assert().success();
+    // It can only be migrated once:
+    context.run("migrate test").assert().failure();
+}
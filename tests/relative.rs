@@ -0,0 +1,72 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+#[test]
+fn test_relative_links() {
+    let mut context = TestBed::new();
+    context
+        .shell("mkdir -p home/proj && echo A=1 > home/proj/.env && echo B=2 > home/proj/b.env")
+        .assert()
+        .success();
+    context
+        .run("init test home/t --relative")
+        .assert()
+        .success();
+    context.run("add test home/proj/.env").assert().success();
+    context
+        .shell("readlink home/proj/.env")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("../t/"));
+    assert_command_output_equals_json(
+        &mut context.binary,
+        "status test --json",
+        serde_json::json!({
+            "backup_name": "test",
+            "files": [{"path": format!("{}/home/proj/.env", context.temp_dir_path), "status": "relative_link"}],
+            "mixed_links": false,
+        }),
+    );
+
+    // Absolute links can be mixed in, and status reports it:
+    context
+        .run("policy test --absolute-links")
+        .assert()
+        .success();
+    context.run("add test home/proj/b.env").assert().success();
+    context
+        .shell("readlink home/proj/b.env")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("/"));
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stdout(contains("linked (relative)"))
+        .stderr(contains("both absolute and relative symlinks"))
+        .stderr(contains("Run `hushcrumbs restore test`"));
+
+    // Restoring with --relative makes them consistent again:
+    context.run("restore test --relative").assert().success();
+    // (only the commands that create symlinks accept it)
+    context.run("ls test --relative").assert().failure();
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stderr(contains("both absolute").not());
+    context
+        .shell("readlink home/proj/b.env")
+        .assert()
+        .success()
+        .stdout(contains("../t/"));
+
+    // The links still resolve when everything is moved together:
+    context
+        .shell("mv home moved && cat moved/proj/.env moved/proj/b.env")
+        .assert()
+        .success()
+        .stdout("A=1\nB=2\n");
+}
@@ -0,0 +1,146 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_render() {
+    let context = TestBed::new();
+    context.run("init work t").assert().success();
+    context
+        .shell("printf 'DB_PASSWORD=hunter2\\n' > .env")
+        .assert()
+        .success();
+    context
+        .shell(r#"printf '{"token": "abc"}' > creds.json"#)
+        .assert()
+        .success();
+    context.run("add work .env").assert().success();
+    context.run("add work creds.json").assert().success();
+    context
+        .shell("printf 'password: {{ hc:work:.env#DB_PASSWORD }}\\ntoken: {{hc:work:creds.json#token}}\\nother: {{ .Values.name }}\\n' > config.tmpl")
+        .assert()
+        .success();
+    context
+        .run("render config.tmpl")
+        .assert()
+        .success()
+        .stdout("password: hunter2\ntoken: abc\nother: {{ .Values.name }}\n");
+}
+
+#[test]
+fn test_render_unresolved() {
+    let context = TestBed::new();
+    context.run("init work t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add work .env").assert().success();
+    context
+        .shell("echo '{{ hc:work:.env#MISSING }} {{ hc:work:nope.env#A }} {{ hc:work }}' > config.tmpl")
+        .assert()
+        .success();
+    context
+        .run("render config.tmpl -o config.yaml")
+        .assert()
+        .failure()
+        .stderr(contains("Unresolved references"))
+        .stderr(contains("MISSING"))
+        .stderr(contains("nope.env"))
+        .stderr(contains("Invalid reference"));
+    assert_path_not_exists(&format!("{}/config.yaml", context.temp_dir_path));
+}
+
+#[test]
+fn test_render_and_add() {
+    let context = TestBed::new();
+    context.run("init work t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add work .env").assert().success();
+    context
+        .shell("echo 'a: {{ hc:work:.env#A }}' > config.tmpl")
+        .assert()
+        .success();
+    context
+        .run("render config.tmpl -o config.yaml --add work")
+        .assert()
+        .success();
+    let config = &format!("{}/config.yaml", context.temp_dir_path);
+    assert_path_is_symlink(config);
+    context
+        .shell("cat config.yaml")
+        .assert()
+        .success()
+        .stdout("a: 1\n");
+    // Rendering again updates the backup file behind the symlink:
+    context.run("set work .env A 2").assert().success();
+    context
+        .run("render config.tmpl -o config.yaml --add work")
+        .assert()
+        .success();
+    assert_path_is_symlink(config);
+    context
+        .shell("cat config.yaml")
+        .assert()
+        .success()
+        .stdout("a: 2\n");
+
+    // Even without --add, the write is audited, and can be undone:
+    context.run("set work .env A 3").assert().success();
+    context
+        .run("render config.tmpl -o config.yaml")
+        .assert()
+        .success();
+    context
+        .shell("grep -c 'operation:\"render\"' t/.audit.ron")
+        .assert()
+        .success()
+        .stdout("3\n");
+    context.run("undo work").assert().success();
+    context
+        .shell("cat config.yaml")
+        .assert()
+        .success()
+        .stdout("a: 2\n");
+}
+
+#[test]
+fn test_render_to_foreign_symlink() {
+    let context = TestBed::new();
+    context.run("init work t").assert().success();
+    context.run("init other o").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context.run("add work .env").assert().success();
+    context
+        .shell("echo 'a: {{ hc:work:.env#A }}' > config.tmpl")
+        .assert()
+        .success();
+    context
+        .shell("echo keep > elsewhere && ln -s elsewhere config.yaml")
+        .assert()
+        .success();
+    // A symlink to a file outside the backup is not written through:
+    context
+        .run("render config.tmpl -o config.yaml")
+        .assert()
+        .failure()
+        .stderr(contains("which is not a backed up file"));
+    context
+        .run("render config.tmpl -o config.yaml --add work")
+        .assert()
+        .failure()
+        .stderr(contains("which is not its file in backup 'work'"));
+    context
+        .shell("cat elsewhere")
+        .assert()
+        .success()
+        .stdout("keep\n");
+
+    // Nor is the symlink of another backup's file:
+    context
+        .shell("rm config.yaml && ln -s .env config.yaml")
+        .assert()
+        .success();
+    context
+        .run("render config.tmpl -o config.yaml --add other")
+        .assert()
+        .failure()
+        .stderr(contains("not its file in backup 'other'"));
+    context.shell("cat .env").assert().success().stdout("A=1\n");
+}
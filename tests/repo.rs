@@ -0,0 +1,94 @@
+mod common;
+use common::*;
+
+fn hushcrumbs() -> String {
+    assert_cmd::cargo::cargo_bin(env!("CARGO_PKG_NAME"))
+        .display()
+        .to_string()
+}
+
+#[test]
+fn test_restore_here() {
+    let mut context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(
+            "git init -q clone1 && git -C clone1 remote add origin git@example.com:team/app.git \
+             && mkdir clone1/api && echo SECRET=1 > clone1/api/.env",
+        )
+        .assert()
+        .success();
+    context
+        .run("add test clone1/api/.env --repo-relative")
+        .assert()
+        .success();
+
+    // A second clone, somewhere else, with the HTTPS URL of the same repository:
+    context
+        .shell(
+            "mkdir elsewhere && git init -q elsewhere/clone2 \
+             && git -C elsewhere/clone2 remote add origin https://example.com/team/app",
+        )
+        .assert()
+        .success();
+    context
+        .shell(&format!(
+            "cd elsewhere/clone2 && {} -c {}/config.ron restore test --here",
+            hushcrumbs(),
+            context.temp_dir_path
+        ))
+        .assert()
+        .success()
+        .stderr(contains("1 file(s) restored"));
+    let clone1 = &format!("{}/clone1/api/.env", context.temp_dir_path);
+    let clone2 = &format!("{}/elsewhere/clone2/api/.env", context.temp_dir_path);
+    assert_path_is_symlink(clone2);
+    assert_eq!(canonicalize(clone1).unwrap(), canonicalize(clone2).unwrap());
+    assert_command_output_equals_json(
+        &mut context.binary,
+        "ls test --json",
+        serde_json::json!({
+            "backup_name": "test",
+            "files": [clone1, clone2],
+            "aliases": {clone2: clone1},
+        }),
+    );
+
+    // Restoring again is harmless:
+    context
+        .shell(&format!(
+            "cd elsewhere/clone2 && {} -c {}/config.ron restore test --here",
+            hushcrumbs(),
+            context.temp_dir_path
+        ))
+        .assert()
+        .success();
+    context.run("rm test clone1/api/.env").assert().success();
+    context
+        .run("get test elsewhere/clone2/api/.env SECRET")
+        .assert()
+        .success()
+        .stdout("1\n");
+}
+
+#[test]
+fn test_repo_relative_errors() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    // Not in a git repository:
+    context.shell("echo A=1 > .env").assert().success();
+    context
+        .run("add test .env --repo-relative")
+        .assert()
+        .failure();
+    assert_regular_file_exists(&format!("{}/.env", context.temp_dir_path));
+    // A repository without any files in the backup:
+    context
+        .shell(&format!(
+            "git init -q other && git -C other remote add origin git@example.com:x/y && cd other && {} -c {}/config.ron restore test --here",
+            hushcrumbs(),
+            context.temp_dir_path
+        ))
+        .assert()
+        .failure();
+}
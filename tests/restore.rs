@@ -1,5 +1,6 @@
 mod common;
 use common::*;
+use predicates::prelude::*;
 
 #[test]
 fn test_restore() {
@@ -196,6 +197,31 @@ fn test_restore_but_paths_file_unreadable() {
         .stderr(contains("Permission denied"));
 }
 
+#[test]
+fn test_restore_but_backup_file_missing() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("echo hi > hi.txt && echo yo > yo.txt")
+        .assert()
+        .success();
+    context.run("add test hi.txt").assert().success();
+    context.run("add test yo.txt").assert().success();
+    context
+        .shell("rm $(readlink hi.txt) && rm yo.txt")
+        .assert()
+        .success();
+    // The dangling symlink is not taken for a valid one, and nothing is restored:
+    context
+        .run("restore test")
+        .assert()
+        .failure()
+        .stderr(contains("the backup file of"))
+        .stderr(contains("cannot be read"))
+        .stderr(contains("panicked").not());
+    assert_path_not_exists(&format!("{}/yo.txt", context.temp_dir_path));
+}
+
 #[test]
 fn test_restore_but_config_file_missing() {
     let context = TestBed::new();
@@ -219,3 +245,247 @@ fn test_restore_but_config_file_corrupt() {
         .failure()
         .stderr(contains("Failed to parse config"));
 }
+
+#[test]
+fn test_restore_on_conflict() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo backup > hi.txt").assert().success();
+    context.run("add test hi.txt").assert().success();
+    let hi = &format!("{}/hi.txt", context.temp_dir_path);
+    let conflict = "rm hi.txt && echo local > hi.txt";
+
+    context.shell(conflict).assert().success();
+    context
+        .run("restore test --on-conflict skip")
+        .assert()
+        .success();
+    assert_regular_file_exists(hi);
+
+    context
+        .run("restore test --on-conflict fail")
+        .assert()
+        .failure()
+        .stderr(contains("A conflicting file exists"));
+    assert_regular_file_exists(hi);
+
+    context
+        .run("restore test --on-conflict stash")
+        .assert()
+        .success();
+    assert_path_is_symlink(hi);
+    context
+        .shell("cat hi.txt.*.stash")
+        .assert()
+        .success()
+        .stdout("local\n");
+
+    context.shell(conflict).assert().success();
+    context
+        .run("restore test --on-conflict adopt")
+        .assert()
+        .success();
+    assert_path_is_symlink(hi);
+    context
+        .shell("cat hi.txt")
+        .assert()
+        .success()
+        .stdout("local\n");
+
+    // (a file left behind by an interrupted run is not overwritten)
+    context
+        .shell("rm hi.txt && echo other > hi.txt && echo leftover > .hi.txt.hushcrumbs-aside")
+        .assert()
+        .success();
+    context
+        .run("restore test --on-conflict overwrite")
+        .assert()
+        .success();
+    context
+        .shell("cat hi.txt .hi.txt.hushcrumbs-aside")
+        .assert()
+        .success()
+        .stdout("local\nleftover\n");
+}
+
+#[test]
+fn test_restore_is_all_or_nothing() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("echo a > a.txt && mkdir b && echo c > b/c.txt")
+        .assert()
+        .success();
+    context.run("add test a.txt").assert().success();
+    context.run("add test b/c.txt").assert().success();
+    let a = &format!("{}/a.txt", context.temp_dir_path);
+
+    // A problem found before restoring anything:
+    context
+        .shell("rm a.txt && rm -r b && touch b")
+        .assert()
+        .success();
+    context
+        .run("restore test")
+        .assert()
+        .failure()
+        .stderr(contains("Nothing was restored"))
+        .stderr(contains("is not a directory"));
+    assert_path_not_exists(a);
+
+    // A failure halfway through is rolled back:
+    context
+        .shell("rm b && f=$(ls t/* | grep -v paths.ron | tail -1) && rm $f && mkdir $f")
+        .assert()
+        .success();
+    context
+        .run("restore test --copy")
+        .assert()
+        .failure()
+        .stderr(contains("rolled back"));
+    assert_path_not_exists(a);
+}
+
+#[test]
+fn test_restore_dry_run() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo a > a.txt").assert().success();
+    context.run("add test a.txt").assert().success();
+    context
+        .shell("rm a.txt && echo local > a.txt")
+        .assert()
+        .success();
+    context
+        .run("restore test --on-conflict stash --dry-run")
+        .assert()
+        .success()
+        .stdout(contains("move the existing file to"))
+        .stdout(contains("and create a symlink"));
+    let a = &format!("{}/a.txt", context.temp_dir_path);
+    assert_regular_file_exists(a);
+    context.shell("ls a.txt.*.stash").assert().failure();
+}
+
+#[test]
+fn test_restore_into_root() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("mkdir -m 700 private && echo a > private/a.txt && mkdir image")
+        .assert()
+        .success();
+    context.run("add test private/a.txt").assert().success();
+    let dir = context
+        .shell("pwd -P")
+        .output()
+        .expect("failed to run pwd")
+        .stdout;
+    let dir = String::from_utf8_lossy(&dir).trim().to_string();
+
+    // The backup is outside of the root, so the file is copied:
+    context
+        .run("restore test --root image")
+        .assert()
+        .success()
+        .stderr(contains("restored into image"));
+    let copy = &format!("image{dir}/private/a.txt");
+    assert_regular_file_exists(&format!("{}/{copy}", context.temp_dir_path));
+    context
+        .shell(&format!("stat -c %a $(dirname {copy})"))
+        .assert()
+        .success()
+        .stdout("700\n");
+    // (and nothing about it is recorded)
+    context
+        .run("status test")
+        .assert()
+        .success()
+        .stdout(contains("image").not());
+
+    // The backup is under the root too, so the file is linked:
+    context
+        .run(&format!("restore test --root {dir}"))
+        .assert()
+        .success();
+    let link = &format!("{dir}{dir}/private/a.txt");
+    assert_path_is_symlink(link);
+    context
+        .shell(&format!("readlink {link}"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(".."));
+
+    context
+        .run("restore test --root missing")
+        .assert()
+        .failure()
+        .stderr(contains("The root directory does not exist"));
+}
+
+#[test]
+fn test_restore_report() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("echo a > a.txt && echo b > b.txt && echo c > c.txt")
+        .assert()
+        .success();
+    context.run("add test a.txt").assert().success();
+    context.run("add test b.txt").assert().success();
+    context.run("add test c.txt").assert().success();
+    context
+        .shell("rm a.txt b.txt && echo local > b.txt")
+        .assert()
+        .success();
+
+    context
+        .run("restore test --on-conflict skip")
+        .assert()
+        .success()
+        .stdout(contains("1 linked, 1 already valid, 1 skipped"));
+
+    context.shell("rm a.txt && mkdir a.txt").assert().success();
+    context
+        .run("restore test --on-conflict overwrite --json")
+        .assert()
+        .failure()
+        .stdout(contains(r#""outcome": "failed""#))
+        .stdout(contains(r#""detail": "a directory is in the way"#))
+        .stdout(contains(r#""not_restored": 2"#))
+        .stderr(contains("Nothing was restored"));
+
+    // Errors found before restoring anything are reported as JSON too:
+    context
+        .run("restore test --on-conflict fail --json")
+        .assert()
+        .failure()
+        .stdout(contains(r#""detail": "A conflicting file exists at"#))
+        .stdout(contains(r#""failed": 2"#));
+    context
+        .run("restore test '*.md' --json")
+        .assert()
+        .failure()
+        .stdout(contains(r#""error": "No files in the backup match""#))
+        .stderr(contains("No files in the backup match"));
+    context
+        .run("restore nope --json")
+        .assert()
+        .failure()
+        .stdout(contains(r#""error": "Backup not found""#));
+
+    // So is a backup file that is missing, with the file it belongs to:
+    context
+        .shell("rmdir a.txt && rm $(readlink c.txt)")
+        .assert()
+        .success();
+    context
+        .run("restore test --on-conflict skip --json")
+        .assert()
+        .failure()
+        .stdout(contains(r#""outcome": "failed""#))
+        .stdout(contains(r#""detail": "the backup file of"#))
+        .stdout(contains("c.txt cannot be read"))
+        .stdout(contains(r#""failed": 1"#))
+        .stdout(contains(r#""not_restored": 2"#));
+}
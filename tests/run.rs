@@ -0,0 +1,63 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_run() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'A=one\\nB=\"two words\"\\n' > .env")
+        .assert()
+        .success();
+    context
+        .shell("printf 'B=override\\nC=three\\n' > prod.env")
+        .assert()
+        .success();
+    context.shell("echo NOT=env > notes.txt").assert().success();
+    context
+        .shell("echo EXAMPLE=placeholder > .env.example")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context.run("add test prod.env").assert().success();
+    context.run("add test notes.txt").assert().success();
+    context.run("add test .env.example").assert().success();
+
+    // All of the dotenv files are merged in order (but not the example):
+    context
+        .run(r#"run test -- sh -c 'echo "$A|$B|$C|$NOT|$EXAMPLE"'"#)
+        .assert()
+        .success()
+        .stdout("one|override|three||\n");
+    // Or only the selected files:
+    context
+        .run(r#"run test --file .env -- sh -c 'echo "$A|$B|$C"'"#)
+        .assert()
+        .success()
+        .stdout("one|two words|\n");
+    context
+        .run(r#"run test -f prod.env -f .env -- sh -c 'echo "$B"'"#)
+        .assert()
+        .success()
+        .stdout("two words\n");
+    // The exit code of the command is passed through:
+    context.run("run test -- false").assert().failure();
+}
+
+#[test]
+fn test_run_errors() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context.shell("echo A=1 > .env").assert().success();
+    context
+        .run("run test --file .env -- true")
+        .assert()
+        .failure()
+        .stderr(contains("File not found in backup"));
+    context.run("add test .env").assert().success();
+    context
+        .run("run test -- command-does-not-exist")
+        .assert()
+        .failure()
+        .stderr(contains("Error running command"));
+}
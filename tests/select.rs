@@ -0,0 +1,93 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+fn setup(context: &TestBed) {
+    context.run("init test t").assert().success();
+    context
+        .shell("mkdir -p web/config api && echo a > web/.env && echo b > web/config/db.env && echo c > api/.env && echo d > notes.txt")
+        .assert()
+        .success();
+    for file in ["web/.env", "web/config/db.env", "api/.env", "notes.txt"] {
+        context.run(&format!("add test {file}")).assert().success();
+    }
+    context
+        .shell("rm web/.env web/config/db.env api/.env notes.txt")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_restore_selected() {
+    let context = TestBed::new();
+    setup(&context);
+    let path = |p: &str| format!("{}/{p}", context.temp_dir_path);
+
+    context.run("restore test --under web").assert().success();
+    assert_path_is_symlink(&path("web/.env"));
+    assert_path_is_symlink(&path("web/config/db.env"));
+    assert_path_not_exists(&path("api/.env"));
+
+    context.run("restore test '*.txt'").assert().success();
+    assert_path_is_symlink(&path("notes.txt"));
+    assert_path_not_exists(&path("api/.env"));
+
+    context
+        .run("restore test '**/*.md'")
+        .assert()
+        .failure()
+        .stderr(contains("No files in the backup match"));
+
+    context.run("restore test api").assert().success();
+    assert_path_is_symlink(&path("api/.env"));
+}
+
+#[test]
+fn test_tag() {
+    let context = TestBed::new();
+    setup(&context);
+    let path = |p: &str| format!("{}/{p}", context.temp_dir_path);
+
+    context
+        .run("tag test web '**/*.env'")
+        .assert()
+        .success()
+        .stderr(contains("3 file(s) tagged 'web'"));
+    context
+        .run("tag test web api --remove")
+        .assert()
+        .success()
+        .stderr(contains("1 file(s) untagged 'web'"));
+    context
+        .run("ls test --tag web")
+        .assert()
+        .success()
+        .stdout(contains("db.env"))
+        .stdout(contains("api").not());
+
+    context.run("restore test --tag web").assert().success();
+    assert_path_is_symlink(&path("web/.env"));
+    assert_path_not_exists(&path("api/.env"));
+    assert_path_not_exists(&path("notes.txt"));
+}
+
+#[test]
+fn test_remove_selected() {
+    let context = TestBed::new();
+    setup(&context);
+    context.run("restore test").assert().success();
+
+    context.run("rm test 'web/**'").assert().success();
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("web").not())
+        .stdout(contains("api/.env"))
+        .stdout(contains("notes.txt"));
+    context
+        .run("rm test --under web")
+        .assert()
+        .failure()
+        .stderr(contains("No files in the backup match"));
+}
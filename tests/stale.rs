@@ -0,0 +1,83 @@
+mod common;
+use common::*;
+use predicates::prelude::*;
+
+/// Pretend that a key was last changed at the start of the unix epoch:
+fn backdate_key(context: &TestBed, key: &str) {
+    context
+        .shell(&format!(
+            "sed -i -E 's/\"{key}\":[0-9]+/\"{key}\":0/' t/paths.ron"
+        ))
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_stale() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'A=1\\nB=2\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    // Without a policy, nothing is stale:
+    context.run("stale test").assert().success();
+    context.run("policy test --max-age 90").assert().success();
+    context.run("stale").assert().success();
+
+    backdate_key(&context, "A");
+    context
+        .run("stale test --json")
+        .assert()
+        .failure()
+        .stdout(contains("\"key\": \"A\""))
+        .stdout(contains("\"max_age_days\": 90"))
+        .stdout(contains("\"key\": \"B\"").not());
+
+    // Rotating the key makes it fresh again:
+    context.run("set test .env A 3").assert().success();
+    context.run("stale test").assert().success();
+
+    // Setting one key doesn't refresh the others:
+    backdate_key(&context, "B");
+    context.run("generate test .env A").assert().success();
+    context
+        .run("stale test")
+        .assert()
+        .failure()
+        .stdout(contains("B"));
+    context.run("policy test --no-max-age").assert().success();
+    context.run("stale test").assert().success();
+    // The policy can be overridden:
+    context
+        .run("stale test --max-age 0 --json")
+        .assert()
+        .failure()
+        .stdout(contains("\"key\": \"A\""));
+}
+
+#[test]
+fn test_stale_edit() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell("printf 'A=1\\nB=2\\n' > .env")
+        .assert()
+        .success();
+    context.run("add test .env").assert().success();
+    context.run("policy test --max-age 30").assert().success();
+    backdate_key(&context, "A");
+    backdate_key(&context, "B");
+    context
+        .run("edit test .env")
+        .env("EDITOR", "sed -i s/A=1/A=2/")
+        .assert()
+        .success();
+    context
+        .run("stale test --json")
+        .assert()
+        .failure()
+        .stdout(contains("\"key\": \"B\""))
+        .stdout(contains("\"key\": \"A\"").not());
+}
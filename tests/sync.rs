@@ -0,0 +1,150 @@
+mod common;
+use common::*;
+
+fn hushcrumbs() -> String {
+    assert_cmd::cargo::cargo_bin(env!("CARGO_PKG_NAME"))
+        .display()
+        .to_string()
+}
+
+/// Run hushcrumbs from a subdirectory of the test directory.
+fn run_in(context: &TestBed, dir: &str, args: &str) -> Command {
+    context.shell(&format!(
+        "cd {dir} && {} -c {}/config.ron {args}",
+        hushcrumbs(),
+        context.temp_dir_path
+    ))
+}
+
+const PROJECT: &str = r#"(files: {".env": "test", "api/.env": "test", "web/.env": "test"})"#;
+
+#[test]
+fn test_sync() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .shell(&format!(
+            "mkdir -p proj/api proj/web && printf '%s' '{PROJECT}' > proj/.hushcrumbs.ron \
+             && echo A=1 > proj/.env && echo B=2 > proj/api/.env"
+        ))
+        .assert()
+        .success();
+    // The declared files are added, and the missing one is reported:
+    run_in(&context, "proj/api", "sync")
+        .assert()
+        .failure()
+        .stderr(contains("Added '.env'"))
+        .stderr(contains("Added 'api/.env'"))
+        .stderr(contains("Missing from backup 'test': web/.env"));
+    assert_path_is_symlink(&format!("{}/proj/.env", context.temp_dir_path));
+    assert_path_is_symlink(&format!("{}/proj/api/.env", context.temp_dir_path));
+
+    // Missing symlinks are linked back into place:
+    context
+        .shell("rm proj/api/.env && echo C=3 > proj/web/.env")
+        .assert()
+        .success();
+    run_in(&context, "proj", "sync")
+        .assert()
+        .success()
+        .stderr(contains("Linked 'api/.env'"))
+        .stderr(contains("Added 'web/.env'"));
+    context.shell("cat proj/api/.env").assert().stdout("B=2\n");
+    run_in(&context, "proj", "sync")
+        .assert()
+        .success()
+        .stderr(contains("All declared files are in sync."));
+
+    // Something else in the way is reported, and left alone:
+    context
+        .shell("rm proj/web/.env && mkdir proj/web/.env")
+        .assert()
+        .success();
+    run_in(&context, "proj", "sync")
+        .assert()
+        .failure()
+        .stderr(contains("Could not sync 'web/.env'"));
+}
+
+#[test]
+fn test_sync_other_clone() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    for clone in ["clone1", "clone2"] {
+        context
+            .shell(&format!(
+                "git init -q {clone} && git -C {clone} remote add origin git@example.com:team/app.git \
+                 && printf '%s' '{PROJECT}' > {clone}/.hushcrumbs.ron"
+            ))
+            .assert()
+            .success();
+    }
+    context
+        .shell("echo A=1 > clone1/.env && mkdir clone1/api && echo B=2 > clone1/api/.env")
+        .assert()
+        .success();
+    run_in(&context, "clone1", "sync").assert().failure();
+    // A fresh clone gets every file the first clone added:
+    run_in(&context, "clone2", "sync")
+        .assert()
+        .failure()
+        .stderr(contains("Linked '.env'"))
+        .stderr(contains("Linked 'api/.env'"))
+        .stderr(contains("Missing from backup 'test': web/.env"));
+    context
+        .shell("cat clone2/api/.env")
+        .assert()
+        .stdout("B=2\n");
+    context
+        .run("ls test")
+        .assert()
+        .success()
+        .stdout(contains("(alias of"));
+}
+
+#[test]
+fn test_sync_without_project() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    context
+        .run("sync")
+        .assert()
+        .failure()
+        .stderr(contains("No .hushcrumbs.ron found"));
+    context
+        .shell("printf '%s' '(files: {\".env\": \"nope\"})' > .hushcrumbs.ron && touch .env")
+        .assert()
+        .success();
+    context
+        .run("sync")
+        .assert()
+        .failure()
+        .stderr(contains("Backup not found: nope"));
+    assert_regular_file_exists(&format!("{}/.env", context.temp_dir_path));
+}
+
+#[test]
+fn test_sync_outside_project() {
+    let context = TestBed::new();
+    context.run("init test t").assert().success();
+    for (file, path) in [
+        ("proj1", "../outside/.env"),
+        ("proj2", "/tmp/.env"),
+        ("proj3", "link/.env"),
+    ] {
+        context
+            .shell(&format!(
+                "mkdir -p {file} outside && ln -sfn ../outside {file}/link \
+                 && printf '%s' '(files: {{\".env\": \"test\", \"{path}\": \"test\"}})' \
+                 > {file}/.hushcrumbs.ron && echo A=1 > {file}/.env"
+            ))
+            .assert()
+            .success();
+        run_in(&context, file, "sync")
+            .assert()
+            .failure()
+            .stderr(contains(format!("{path} is outside the project directory")));
+        // Nothing was synced:
+        assert_regular_file_exists(&format!("{}/{file}/.env", context.temp_dir_path));
+    }
+}